@@ -0,0 +1,365 @@
+//! Audio quality report for `stt-typer analyze`: level, clipping, DC offset, an
+//! SNR estimate and where the background noise sits in the spectrum, with advice
+//! on fixing the setup. Nothing is transcribed.
+
+use std::fmt;
+
+/// Sample rate of the analyzed audio (what Whisper expects).
+const SAMPLE_RATE: f32 = 16000.0;
+/// Analysis frame length (32 ms); gives 31.25 Hz spectral resolution.
+const FRAME: usize = 512;
+/// Cap on the quiet frames whose spectrum is computed.
+const MAX_NOISE_FRAMES: usize = 64;
+/// Frames below this level count as silence.
+const SILENCE_DBFS: f32 = -50.0;
+/// Recordings peaking below this level are too quiet to transcribe reliably.
+const QUIET_PEAK_DBFS: f32 = -20.0;
+/// Samples at or above this magnitude count as clipped.
+const CLIP_LEVEL: f32 = 0.999;
+/// Noise bands reported, as (low Hz, high Hz, description).
+const BANDS: [(f32, f32, &str); 5] = [
+    (0.0, 120.0, "under 120 Hz hum/rumble"),
+    (120.0, 300.0, "120–300 Hz"),
+    (300.0, 1000.0, "300 Hz–1 kHz"),
+    (1000.0, 4000.0, "1–4 kHz"),
+    (4000.0, 8000.0, "4–8 kHz hiss"),
+];
+
+/// Measurements of a 16kHz mono recording.
+pub struct Report {
+    pub duration_secs: f32,
+    pub levels: Levels,
+    /// Fraction of samples at full scale (0–1).
+    pub clipped: f32,
+    /// Mean sample value.
+    pub dc_offset: f32,
+    /// Loud (speech) frame level over quiet (noise) frame level; `None` if the
+    /// quiet frames are digital silence.
+    pub snr_db: Option<f32>,
+    /// Share of background noise energy per band, loudest first.
+    pub noise_bands: Vec<(&'static str, f32)>,
+}
+
+fn dbfs(level: f32) -> f32 {
+    20.0 * level.max(1e-10).log10()
+}
+
+/// Level summary logged with every transcription.
+pub struct Levels {
+    pub peak_dbfs: f32,
+    pub rms_dbfs: f32,
+    /// Fraction of 32 ms frames below [`SILENCE_DBFS`] (0–1).
+    pub silence: f32,
+}
+
+/// Peak, RMS and share of silent frames of 16kHz mono samples.
+pub fn levels(samples: &[f32]) -> Levels {
+    let n = samples.len().max(1) as f32;
+    let peak = samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+    let rms = (samples.iter().map(|s| s * s).sum::<f32>() / n).sqrt();
+    let frames = samples.chunks(FRAME).count().max(1);
+    let silent = samples
+        .chunks(FRAME)
+        .filter(|frame| {
+            let rms = (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt();
+            dbfs(rms) < SILENCE_DBFS
+        })
+        .count();
+    Levels {
+        peak_dbfs: dbfs(peak),
+        rms_dbfs: dbfs(rms),
+        silence: silent as f32 / frames as f32,
+    }
+}
+
+impl Levels {
+    /// Why a recording may have produced no usable text, if its levels explain it.
+    pub fn advice(&self) -> Option<&'static str> {
+        if self.peak_dbfs < SILENCE_DBFS {
+            Some(
+                "the recording is silent: check that the right input device is selected and unmuted",
+            )
+        } else if self.peak_dbfs < QUIET_PEAK_DBFS {
+            Some("the mic is very quiet: increase the input gain or move closer to the mic")
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Levels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "peak {:.1} dBFS, rms {:.1} dBFS, {:.0}% silence",
+            self.peak_dbfs,
+            self.rms_dbfs,
+            self.silence * 100.0
+        )
+    }
+}
+
+/// Analyze 16kHz mono samples.
+pub fn analyze(samples: &[f32]) -> Report {
+    let n = samples.len().max(1) as f32;
+    let clipped = samples.iter().filter(|s| s.abs() >= CLIP_LEVEL).count() as f32 / n;
+    let dc_offset = samples.iter().sum::<f32>() / n;
+
+    // Rank frames by level: the quietest are background noise, the loudest speech.
+    let mut frames: Vec<(f32, &[f32])> = samples
+        .chunks_exact(FRAME)
+        .map(|frame| {
+            let mean = frame.iter().sum::<f32>() / FRAME as f32;
+            let energy = frame.iter().map(|s| (s - mean) * (s - mean)).sum::<f32>();
+            ((energy / FRAME as f32).sqrt(), frame)
+        })
+        .collect();
+    frames.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let (snr_db, noise_bands) = if frames.len() < 10 {
+        (None, Vec::new())
+    } else {
+        let noise = frames[frames.len() / 10].0;
+        let speech = frames[frames.len() * 95 / 100].0;
+        let snr = (noise > 1e-6).then(|| dbfs(speech) - dbfs(noise));
+        let quiet = &frames[..(frames.len() / 5).min(MAX_NOISE_FRAMES)];
+        (snr, noise_bands(quiet.iter().map(|(_, f)| *f)))
+    };
+
+    Report {
+        duration_secs: samples.len() as f32 / SAMPLE_RATE,
+        levels: levels(samples),
+        clipped,
+        dc_offset,
+        snr_db,
+        noise_bands,
+    }
+}
+
+/// Distribute the spectral energy of the quiet frames over [`BANDS`].
+fn noise_bands<'a>(frames: impl Iterator<Item = &'a [f32]>) -> Vec<(&'static str, f32)> {
+    let mut energy = [0.0f32; BANDS.len()];
+    let window: Vec<f32> = (0..FRAME)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / FRAME as f32).cos())
+        .collect();
+
+    for frame in frames {
+        let mean = frame.iter().sum::<f32>() / FRAME as f32;
+        let windowed: Vec<f32> = frame
+            .iter()
+            .zip(&window)
+            .map(|(s, w)| (s - mean) * w)
+            .collect();
+        // Plain DFT: only a handful of quiet frames are analyzed.
+        for k in 1..FRAME / 2 {
+            let freq = k as f32 * SAMPLE_RATE / FRAME as f32;
+            let step = 2.0 * std::f32::consts::PI * k as f32 / FRAME as f32;
+            let (mut re, mut im) = (0.0f32, 0.0f32);
+            for (i, s) in windowed.iter().enumerate() {
+                let (sin, cos) = (step * i as f32).sin_cos();
+                re += s * cos;
+                im -= s * sin;
+            }
+            if let Some(band) = BANDS
+                .iter()
+                .position(|&(lo, hi, _)| freq >= lo && freq < hi)
+            {
+                energy[band] += re * re + im * im;
+            }
+        }
+    }
+
+    let total: f32 = energy.iter().sum();
+    if total <= 0.0 {
+        return Vec::new();
+    }
+    let mut bands: Vec<(&'static str, f32)> = BANDS
+        .iter()
+        .zip(energy)
+        .map(|(&(_, _, name), e)| (name, e / total))
+        .collect();
+    bands.sort_by(|a, b| b.1.total_cmp(&a.1));
+    bands
+}
+
+impl Report {
+    /// Suggestions for improving the recording, most important first.
+    pub fn recommendations(&self) -> Vec<String> {
+        let mut advice = Vec::new();
+        if self.clipped > 0.001 {
+            advice.push(
+                "audio is clipping: lower the input gain or move back from the mic".to_string(),
+            );
+        } else if self.levels.peak_dbfs < QUIET_PEAK_DBFS {
+            advice.push(
+                "levels are low: increase the input gain or move closer to the mic".to_string(),
+            );
+        }
+        if self.dc_offset.abs() > 0.01 {
+            advice.push(
+                "large DC offset: the mic or interface is biasing the signal; try another input"
+                    .to_string(),
+            );
+        }
+        if let Some(snr) = self.snr_db
+            && snr < 15.0
+        {
+            let strongest = match self.noise_bands.first() {
+                Some((band, _)) => format!(", noise strongest at {band}"),
+                None => String::new(),
+            };
+            advice.push(format!(
+                "noisy recording ({snr:.0} dB SNR{strongest}): reduce background noise \
+                 and enable --vad-model to skip non-speech"
+            ));
+        }
+        if advice.is_empty() {
+            advice.push("audio looks good".to_string());
+        }
+        advice
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "duration:     {:.1}s", self.duration_secs)?;
+        writeln!(f, "peak:         {:.1} dBFS", self.levels.peak_dbfs)?;
+        writeln!(f, "rms:          {:.1} dBFS", self.levels.rms_dbfs)?;
+        writeln!(
+            f,
+            "silence:      {:.0}% of frames",
+            self.levels.silence * 100.0
+        )?;
+        writeln!(f, "clipping:     {:.2}% of samples", self.clipped * 100.0)?;
+        writeln!(f, "dc offset:    {:+.4}", self.dc_offset)?;
+        match self.snr_db {
+            Some(snr) => writeln!(f, "snr estimate: {snr:.1} dB")?,
+            None => writeln!(f, "snr estimate: n/a")?,
+        }
+        if !self.noise_bands.is_empty() {
+            let bands: Vec<String> = self
+                .noise_bands
+                .iter()
+                .take(2)
+                .map(|(name, share)| format!("{name} {:.0}%", share * 100.0))
+                .collect();
+            writeln!(f, "noise bands:  {}", bands.join(", "))?;
+        }
+        for tip in self.recommendations() {
+            writeln!(f, "recommendation: {tip}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::sine;
+
+    #[test]
+    fn levels_of_tone_followed_by_silence() {
+        let mut samples = sine(440.0, 1.0, 16000);
+        samples.extend(vec![0.0; 16000]);
+        let levels = levels(&samples);
+        assert!((levels.peak_dbfs - dbfs(0.5)).abs() < 0.1);
+        // A 0.5 amplitude sine has RMS 0.354; half the recording is silent.
+        assert!((levels.rms_dbfs - dbfs(0.354 / 2f32.sqrt())).abs() < 0.1);
+        assert!((levels.silence - 0.5).abs() < 0.02, "{}", levels.silence);
+        assert!(levels.advice().is_none());
+    }
+
+    #[test]
+    fn advice_for_quiet_and_silent_recordings() {
+        let quiet: Vec<f32> = sine(440.0, 1.0, 16000).iter().map(|s| s * 0.02).collect();
+        assert!(levels(&quiet).advice().unwrap().contains("quiet"));
+        assert!(
+            levels(&vec![0.0; 16000])
+                .advice()
+                .unwrap()
+                .contains("silent")
+        );
+        assert_eq!(levels(&[]).silence, 0.0);
+    }
+
+    /// Deterministic noise in -amplitude..amplitude.
+    fn noise(len: usize, amplitude: f32) -> Vec<f32> {
+        let mut state = 12345u32;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+                ((state >> 8) as f32 / (1u32 << 24) as f32 * 2.0 - 1.0) * amplitude
+            })
+            .collect()
+    }
+
+    fn mix(a: &[f32], b: &[f32]) -> Vec<f32> {
+        a.iter().zip(b).map(|(a, b)| a + b).collect()
+    }
+
+    #[test]
+    fn clean_tone_looks_good() {
+        let mut speech = vec![0.0; 16000];
+        speech.extend(sine(440.0, 1.0, 16000));
+        let report = analyze(&mix(&speech, &noise(32000, 0.001)));
+        assert!((report.duration_secs - 2.0).abs() < 1e-3);
+        assert!(report.snr_db.unwrap() > 30.0, "{:?}", report.snr_db);
+        assert_eq!(report.recommendations(), ["audio looks good"]);
+    }
+
+    #[test]
+    fn too_short_for_an_snr_estimate() {
+        let report = analyze(&sine(440.0, 0.25, 16000));
+        assert_eq!(report.snr_db, None);
+        assert!(report.noise_bands.is_empty());
+    }
+
+    #[test]
+    fn noisy_recording() {
+        // Speech-level tone in the second half over constant noise.
+        let mut speech = vec![0.0; 16000];
+        speech.extend(sine(440.0, 1.0, 16000));
+        let report = analyze(&mix(&speech, &noise(32000, 0.15)));
+        let snr = report.snr_db.unwrap();
+        assert!(snr < 15.0, "{snr}");
+        let advice = report.recommendations();
+        assert_eq!(advice.len(), 1, "{advice:?}");
+        assert!(advice[0].starts_with("noisy recording"), "{advice:?}");
+    }
+
+    #[test]
+    fn hum_is_the_strongest_noise_band() {
+        // 50 Hz hum throughout, a 1 kHz tone only in the second half.
+        let hum: Vec<f32> = sine(50.0, 2.0, 16000).iter().map(|s| s * 0.02).collect();
+        let mut speech = vec![0.0; 16000];
+        speech.extend(sine(1000.0, 1.0, 16000));
+        let report = analyze(&mix(&hum, &speech));
+        let (band, share) = report.noise_bands[0];
+        assert_eq!(band, "under 120 Hz hum/rumble");
+        assert!(share > 0.9, "{share}");
+        let total: f32 = report.noise_bands.iter().map(|(_, s)| s).sum();
+        assert!((total - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn level_recommendations() {
+        let clipped: Vec<f32> = sine(440.0, 1.0, 16000)
+            .iter()
+            .map(|s| (s * 4.0).clamp(-1.0, 1.0))
+            .collect();
+        assert!(analyze(&clipped).recommendations()[0].starts_with("audio is clipping"));
+
+        let quiet: Vec<f32> = sine(440.0, 1.0, 16000).iter().map(|s| s * 0.1).collect();
+        let report = analyze(&quiet);
+        assert!(report.recommendations()[0].starts_with("levels are low"));
+        // The report and the per-recording hint agree on what is too quiet.
+        assert!(report.levels.advice().is_some());
+
+        let offset: Vec<f32> = sine(440.0, 1.0, 16000).iter().map(|s| s + 0.05).collect();
+        let advice = analyze(&offset).recommendations();
+        assert!(
+            advice.iter().any(|tip| tip.starts_with("large DC offset")),
+            "{advice:?}"
+        );
+    }
+}
@@ -1,6 +1,10 @@
+use crate::pcm;
 use anyhow::{Context, Result, bail};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::SampleFormat;
+use cpal::{BufferSize, SampleFormat, SampleRate, SupportedBufferSize, SupportedStreamConfig};
+use std::io::Read;
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
@@ -10,43 +14,322 @@ struct StreamHandle {
     samples: Arc<Mutex<Vec<f32>>>,
     device_rate: u32,
     channels: usize,
+    resample_quality: ResampleQuality,
 }
 
-fn start_recording() -> Result<StreamHandle> {
+/// Capture settings applied when opening the input device.
+#[derive(Clone, Default)]
+pub struct CaptureOptions {
+    /// Preferred device sample rate in Hz; falls back to the device default if unsupported.
+    pub sample_rate: Option<u32>,
+    /// Preferred buffer size in frames; falls back to the device default if unsupported.
+    pub buffer_size: Option<u32>,
+    /// Input device names to capture from and mix; empty means the default device.
+    pub devices: Vec<String>,
+    /// Resampler used to convert the device rate to 16kHz.
+    pub resample_quality: ResampleQuality,
+    /// Read raw PCM from this pipe instead of opening an input device.
+    pub pipe: Option<Arc<PipeInput>>,
+}
+
+/// Raw PCM read from a named pipe (FIFO), e.g. one fed by `pacat` from a
+/// PulseAudio null sink or by an SDR receiver. The pipe is drained continuously
+/// on a background thread so the writer never blocks; only audio that arrives
+/// while recording is kept.
+pub struct PipeInput {
+    raw: pcm::RawPcm,
+    recording: Arc<AtomicBool>,
+    bytes: Arc<Mutex<Vec<u8>>>,
+}
+
+impl PipeInput {
+    /// Start draining the FIFO at `path`, whose samples are described by `raw`.
+    /// Returns immediately; the pipe is opened once a writer connects.
+    pub fn open(path: &Path, raw: pcm::RawPcm) -> Result<Self> {
+        let metadata = std::fs::metadata(path).with_context(|| {
+            format!(
+                "input pipe {} not found (create it with mkfifo)",
+                path.display()
+            )
+        })?;
+        if !metadata.file_type().is_fifo() {
+            bail!(
+                "{} is not a named pipe (create one with mkfifo)",
+                path.display()
+            );
+        }
+        if raw.channels == 0 {
+            bail!("raw PCM input needs at least one channel");
+        }
+        if raw.rate == 0 {
+            bail!("raw PCM input needs a sample rate above 0 Hz");
+        }
+
+        let recording = Arc::new(AtomicBool::new(false));
+        let bytes = Arc::new(Mutex::new(Vec::new()));
+        let (r, b, path) = (recording.clone(), bytes.clone(), path.to_path_buf());
+        let frame = raw.frame_size();
+        std::thread::spawn(move || drain_pipe(&path, frame, &r, &b));
+        Ok(PipeInput {
+            raw,
+            recording,
+            bytes,
+        })
+    }
+
+    fn record_until_stopped(
+        &self,
+        stop: &AtomicBool,
+        max_duration: Duration,
+        quality: ResampleQuality,
+    ) -> Result<Vec<f32>> {
+        self.bytes.lock().unwrap().clear();
+        self.recording.store(true, Ordering::Relaxed);
+        wait_for_stop(stop, max_duration);
+        self.recording.store(false, Ordering::Relaxed);
+
+        let bytes = std::mem::take(&mut *self.bytes.lock().unwrap());
+        let decoded = pcm::decode_raw(&bytes, &self.raw)?;
+        Ok(to_whisper_format(
+            decoded.samples,
+            decoded.rate,
+            decoded.channels as usize,
+            quality,
+        ))
+    }
+}
+
+/// Read `path` forever, keeping bytes only while `recording` is set. When the
+/// writer goes away the pipe is reopened, which waits for the next writer.
+fn drain_pipe(path: &Path, frame: usize, recording: &AtomicBool, bytes: &Mutex<Vec<u8>>) {
+    let mut buf = [0u8; 8192];
+    loop {
+        // Bytes read from this writer, to find frame boundaries in later reads.
+        let mut offset = 0u64;
+        let mut file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!(
+                    "[stt-typer] failed to open input pipe {}: {e}",
+                    path.display()
+                );
+                std::thread::sleep(Duration::from_secs(1));
+                continue;
+            }
+        };
+        eprintln!("[stt-typer] input pipe {} connected", path.display());
+        loop {
+            match file.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    if recording.load(Ordering::Relaxed) {
+                        keep_frames(&mut bytes.lock().unwrap(), &buf[..n], offset, frame);
+                    }
+                    offset += n as u64;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    eprintln!("[stt-typer] input pipe read failed: {e}");
+                    break;
+                }
+            }
+        }
+        eprintln!("[stt-typer] input pipe writer disconnected, waiting for a new one");
+    }
+}
+
+/// Append `chunk`, which starts `offset` bytes into the stream, to `kept`. The
+/// first bytes kept start at a frame boundary: reads return arbitrary byte
+/// counts, so the chunk may begin mid-sample or mid-frame.
+fn keep_frames(kept: &mut Vec<u8>, chunk: &[u8], offset: u64, frame: usize) {
+    let skip = if kept.is_empty() {
+        (frame - (offset % frame as u64) as usize) % frame
+    } else {
+        0
+    };
+    kept.extend_from_slice(&chunk[skip.min(chunk.len())..]);
+}
+
+/// Resampler used to convert audio to 16kHz, trading CPU time for fidelity.
+#[derive(Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+pub enum ResampleQuality {
+    /// Linear interpolation; cheapest, but aliases high frequencies when downsampling.
+    #[default]
+    Fast,
+    /// Short windowed-sinc filter from a precomputed polyphase table.
+    Balanced,
+    /// Long windowed-sinc filter with a denser polyphase table; slower, sharper cutoff.
+    High,
+}
+
+/// Names of all available audio input devices.
+pub fn list_input_devices() -> Result<Vec<String>> {
+    let host = cpal::default_host();
+    let devices = host
+        .input_devices()
+        .context("failed to enumerate input devices")?;
+    Ok(devices.filter_map(|d| d.name().ok()).collect())
+}
+
+/// Fail early if any configured device name doesn't match an input device.
+pub fn check_input_devices(names: &[String]) -> Result<()> {
+    input_devices(names).map(|_| ())
+}
+
+/// Resolve the configured device names (exact match first, then case-insensitive
+/// substring), or the default input device if none are configured.
+fn input_devices(names: &[String]) -> Result<Vec<cpal::Device>> {
     let host = cpal::default_host();
-    let device = host
-        .default_input_device()
-        .context("no audio input device available")?;
+    if names.is_empty() {
+        let device = host
+            .default_input_device()
+            .context("no audio input device available")?;
+        return Ok(vec![device]);
+    }
+
+    let available: Vec<(String, cpal::Device)> = host
+        .input_devices()
+        .context("failed to enumerate input devices")?
+        .filter_map(|d| Some((d.name().ok()?, d)))
+        .collect();
+
+    names
+        .iter()
+        .map(|wanted| {
+            let lower = wanted.to_lowercase();
+            available
+                .iter()
+                .find(|(name, _)| name == wanted)
+                .or_else(|| {
+                    available
+                        .iter()
+                        .find(|(name, _)| name.to_lowercase().contains(&lower))
+                })
+                .map(|(_, device)| device.clone())
+                .with_context(|| {
+                    let names: Vec<&str> = available.iter().map(|(n, _)| n.as_str()).collect();
+                    format!(
+                        "no input device matching \"{wanted}\" (available: {})",
+                        names.join(", ")
+                    )
+                })
+        })
+        .collect()
+}
 
-    let supported = device
+fn start_recording(device: &cpal::Device, opts: &CaptureOptions) -> Result<StreamHandle> {
+    let default = device
         .default_input_config()
         .context("failed to get default input config")?;
 
-    let device_rate = supported.sample_rate().0;
-    let channels = supported.channels() as usize;
-    let stream_config: cpal::StreamConfig = supported.clone().into();
+    let supported = match opts.sample_rate {
+        Some(rate) => config_with_rate(device, &default, rate).unwrap_or_else(|| {
+            eprintln!(
+                "[stt-typer] input device does not support {rate} Hz, using {} Hz",
+                default.sample_rate().0
+            );
+            default.clone()
+        }),
+        None => default.clone(),
+    };
+
+    let mut stream_config: cpal::StreamConfig = supported.clone().into();
+    if let Some(frames) = opts.buffer_size {
+        match check_buffer_size(supported.buffer_size(), frames) {
+            Ok(()) => stream_config.buffer_size = BufferSize::Fixed(frames),
+            Err(e) => eprintln!("[stt-typer] {e}, using default"),
+        }
+    }
 
     let samples: Arc<Mutex<Vec<f32>>> = Arc::new(Mutex::new(Vec::new()));
-    let samples_w = samples.clone();
+
+    let overridden = opts.sample_rate.is_some() || opts.buffer_size.is_some();
+    let (stream, config) =
+        match build_input_stream(device, &stream_config, supported.sample_format(), &samples) {
+            Ok(stream) => (stream, stream_config),
+            Err(e) if overridden => {
+                eprintln!("[stt-typer] capture overrides rejected ({e}), using device defaults");
+                let config: cpal::StreamConfig = default.clone().into();
+                let stream =
+                    build_input_stream(device, &config, default.sample_format(), &samples)?;
+                (stream, config)
+            }
+            Err(e) => return Err(e),
+        };
+
+    stream.play().context("failed to start audio stream")?;
+
+    Ok(StreamHandle {
+        _stream: stream,
+        samples,
+        device_rate: config.sample_rate.0,
+        channels: config.channels as usize,
+        resample_quality: opts.resample_quality,
+    })
+}
+
+/// Find a supported input config running at `rate`, preferring the default
+/// config's channel count and F32 samples.
+fn config_with_rate(
+    device: &cpal::Device,
+    default: &SupportedStreamConfig,
+    rate: u32,
+) -> Option<SupportedStreamConfig> {
+    device
+        .supported_input_configs()
+        .ok()?
+        .filter(|range| {
+            matches!(range.sample_format(), SampleFormat::F32 | SampleFormat::I16)
+                && (range.min_sample_rate().0..=range.max_sample_rate().0).contains(&rate)
+        })
+        .max_by_key(|range| {
+            (
+                range.channels() == default.channels(),
+                range.sample_format() == SampleFormat::F32,
+            )
+        })
+        .map(|range| range.with_sample_rate(SampleRate(rate)))
+}
+
+/// Check that a device accepts a fixed buffer of `frames`. Devices that don't
+/// report a range are given the benefit of the doubt.
+fn check_buffer_size(supported: &SupportedBufferSize, frames: u32) -> Result<(), String> {
+    match supported {
+        SupportedBufferSize::Range { min, max } if !(*min..=*max).contains(&frames) => Err(
+            format!("buffer size {frames} outside device range {min}-{max}"),
+        ),
+        _ => Ok(()),
+    }
+}
+
+fn build_input_stream(
+    device: &cpal::Device,
+    stream_config: &cpal::StreamConfig,
+    sample_format: SampleFormat,
+    samples: &Arc<Mutex<Vec<f32>>>,
+) -> Result<cpal::Stream> {
     let err_flag: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
-    let err_w = err_flag.clone();
-
-    let stream = match supported.sample_format() {
-        SampleFormat::F32 => device.build_input_stream(
-            &stream_config,
-            move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                samples_w.lock().unwrap().extend_from_slice(data);
-            },
-            move |e| {
-                *err_w.lock().unwrap() = Some(format!("{e}"));
-            },
-            None,
-        )?,
+
+    let stream = match sample_format {
+        SampleFormat::F32 => {
+            let sw = samples.clone();
+            let ew = err_flag.clone();
+            device.build_input_stream(
+                stream_config,
+                move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                    sw.lock().unwrap().extend_from_slice(data);
+                },
+                move |e| {
+                    *ew.lock().unwrap() = Some(format!("{e}"));
+                },
+                None,
+            )?
+        }
         SampleFormat::I16 => {
             let sw = samples.clone();
             let ew = err_flag.clone();
             device.build_input_stream(
-                &stream_config,
+                stream_config,
                 move |data: &[i16], _: &cpal::InputCallbackInfo| {
                     sw.lock().unwrap()
                         .extend(data.iter().map(|&s| s as f32 / i16::MAX as f32));
@@ -60,51 +343,95 @@ fn start_recording() -> Result<StreamHandle> {
         fmt => bail!("unsupported sample format: {fmt:?}"),
     };
 
-    stream.play().context("failed to start audio stream")?;
-
-    Ok(StreamHandle {
-        _stream: stream,
-        samples,
-        device_rate,
-        channels,
-    })
+    Ok(stream)
 }
 
 fn finish_recording(handle: StreamHandle) -> Vec<f32> {
     drop(handle._stream);
     let raw = handle.samples.lock().unwrap().clone();
+    to_whisper_format(
+        raw,
+        handle.device_rate,
+        handle.channels,
+        handle.resample_quality,
+    )
+}
 
+/// Convert interleaved samples at any rate and channel count to the 16kHz mono
+/// f32 samples Whisper expects.
+pub fn to_whisper_format(
+    raw: Vec<f32>,
+    rate: u32,
+    channels: usize,
+    quality: ResampleQuality,
+) -> Vec<f32> {
     // Convert to mono if stereo
-    let mono = if handle.channels >= 2 {
-        raw.chunks(handle.channels)
-            .map(|frame| frame.iter().sum::<f32>() / handle.channels as f32)
+    let mono = if channels >= 2 {
+        raw.chunks(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
             .collect()
     } else {
         raw
     };
 
     // Resample to 16kHz if needed
-    if handle.device_rate == 16000 {
-        mono
-    } else {
-        resample(&mono, handle.device_rate, 16000)
+    match quality {
+        _ if rate == 16000 => mono,
+        ResampleQuality::Fast => resample(&mono, rate, 16000),
+        ResampleQuality::Balanced => resample_sinc(
+            &mono,
+            rate,
+            16000,
+            BALANCED_ZERO_CROSSINGS,
+            BALANCED_MAX_PHASES,
+        ),
+        ResampleQuality::High => {
+            resample_sinc(&mono, rate, 16000, HIGH_ZERO_CROSSINGS, HIGH_MAX_PHASES)
+        }
+    }
+}
+
+/// Average several 16kHz mono tracks into one. Tracks may differ slightly in
+/// length since their streams start a few milliseconds apart.
+fn mix(tracks: Vec<Vec<f32>>) -> Vec<f32> {
+    if tracks.len() == 1 {
+        return tracks.into_iter().next().unwrap_or_default();
     }
+    let len = tracks.iter().map(Vec::len).max().unwrap_or(0);
+    (0..len)
+        .map(|i| {
+            let present: Vec<f32> = tracks.iter().filter_map(|t| t.get(i).copied()).collect();
+            present.iter().sum::<f32>() / present.len() as f32
+        })
+        .collect()
 }
 
 /// Record audio until `stop` is set to true, or `max_duration` elapses.
+/// With several devices configured, they are captured simultaneously and mixed;
+/// with a pipe configured, it is read instead of any device.
 /// Returns 16kHz mono f32 samples suitable for Whisper.
-pub fn record_until_stopped(stop: Arc<AtomicBool>, max_duration: Duration) -> Result<Vec<f32>> {
-    let handle = start_recording()?;
-    let start = Instant::now();
+pub fn record_until_stopped(
+    stop: Arc<AtomicBool>,
+    max_duration: Duration,
+    opts: &CaptureOptions,
+) -> Result<Vec<f32>> {
+    if let Some(pipe) = &opts.pipe {
+        return pipe.record_until_stopped(&stop, max_duration, opts.resample_quality);
+    }
+    let handles = input_devices(&opts.devices)?
+        .iter()
+        .map(|device| start_recording(device, opts))
+        .collect::<Result<Vec<_>>>()?;
+    wait_for_stop(&stop, max_duration);
 
-    loop {
-        if stop.load(Ordering::Relaxed) || start.elapsed() >= max_duration {
-            break;
-        }
+    Ok(mix(handles.into_iter().map(finish_recording).collect()))
+}
+
+fn wait_for_stop(stop: &AtomicBool, max_duration: Duration) {
+    let start = Instant::now();
+    while !stop.load(Ordering::Relaxed) && start.elapsed() < max_duration {
         std::thread::sleep(Duration::from_millis(10));
     }
-
-    Ok(finish_recording(handle))
 }
 
 /// Simple linear interpolation resampler.
@@ -128,3 +455,356 @@ fn resample(input: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
     }
     output
 }
+
+/// Clean up a 16kHz mono recording before transcription: subtract any DC offset,
+/// then high-pass filter at `cutoff_hz` to remove rumble and handling noise.
+pub fn remove_rumble(samples: &mut [f32], cutoff_hz: f32) {
+    remove_dc(samples);
+    highpass(samples, cutoff_hz, 16000);
+}
+
+/// Subtract the mean so the signal is centred on zero.
+fn remove_dc(samples: &mut [f32]) {
+    if samples.is_empty() {
+        return;
+    }
+    let mean = samples.iter().map(|&s| s as f64).sum::<f64>() / samples.len() as f64;
+    for s in samples.iter_mut() {
+        *s -= mean as f32;
+    }
+}
+
+/// Second-order Butterworth high-pass (RBJ biquad, Q = 1/√2), 12 dB per octave
+/// below `cutoff_hz`.
+fn highpass(samples: &mut [f32], cutoff_hz: f32, rate: u32) {
+    let w0 = 2.0 * std::f64::consts::PI * cutoff_hz as f64 / rate as f64;
+    let alpha = w0.sin() / std::f64::consts::SQRT_2;
+    let cos = w0.cos();
+    let a0 = 1.0 + alpha;
+    let b0 = (1.0 + cos) / 2.0 / a0;
+    let b1 = -(1.0 + cos) / a0;
+    let b2 = b0;
+    let a1 = -2.0 * cos / a0;
+    let a2 = (1.0 - alpha) / a0;
+
+    let (mut x1, mut x2, mut y1, mut y2) = (0.0, 0.0, 0.0, 0.0);
+    for s in samples.iter_mut() {
+        let x = *s as f64;
+        let y = b0 * x + b1 * x1 + b2 * x2 - a1 * y1 - a2 * y2;
+        (x2, x1, y2, y1) = (x1, x, y1, y);
+        *s = y as f32;
+    }
+}
+
+/// Sinc lobes on each side of the `balanced` and `high` filter kernels.
+const BALANCED_ZERO_CROSSINGS: usize = 8;
+const HIGH_ZERO_CROSSINGS: usize = 32;
+
+/// Most kernel phases precomputed by the `balanced` and `high` resamplers. Common
+/// rate pairs need far fewer (44.1kHz → 16kHz has 160) and get exact phases;
+/// odd rates are rounded to the nearest of this many.
+const BALANCED_MAX_PHASES: usize = 256;
+const HIGH_MAX_PHASES: usize = 4096;
+
+/// Low-pass the passband to this fraction of the lower Nyquist frequency, leaving
+/// room for the filter's transition band.
+const SINC_ROLLOFF: f64 = 0.95;
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Band-limited polyphase resampler: each output sample is a Blackman-windowed
+/// sinc interpolation of the input, low-passed below the lower of the two
+/// Nyquist frequencies. Kernel weights are computed once per phase, i.e. per
+/// distinct fractional input position, up to `max_phases` of them.
+fn resample_sinc(
+    input: &[f32],
+    from_rate: u32,
+    to_rate: u32,
+    zero_crossings: usize,
+    max_phases: usize,
+) -> Vec<f32> {
+    if input.is_empty() {
+        return Vec::new();
+    }
+    let ratio = from_rate as f64 / to_rate as f64;
+    // Cutoff relative to the input Nyquist frequency.
+    let cutoff = SINC_ROLLOFF * (1.0 / ratio).min(1.0);
+    // Kernel half-width in input samples.
+    let half_width = zero_crossings as f64 / cutoff;
+    let taps = half_width.ceil() as isize;
+
+    let kernel = |t: f64| -> f64 {
+        if t.abs() >= half_width {
+            return 0.0;
+        }
+        let x = std::f64::consts::PI * cutoff * t;
+        let sinc = if x == 0.0 { 1.0 } else { x.sin() / x };
+        let w = std::f64::consts::PI * t / half_width;
+        let blackman = 0.42 + 0.5 * w.cos() + 0.08 * (2.0 * w).cos();
+        sinc * blackman
+    };
+    // Weights for input offsets -taps+1..=taps around a fractional position,
+    // normalised to unit gain so DC and edge samples keep their level.
+    let weights = |frac: f64| -> Vec<f64> {
+        let w: Vec<f64> = (-taps + 1..=taps)
+            .map(|k| kernel(k as f64 - frac))
+            .collect();
+        let sum: f64 = w.iter().sum();
+        w.into_iter().map(|x| x / sum).collect()
+    };
+
+    // Output sample i sits at input position i * step / phases. With few enough
+    // phases each gets an exact row; otherwise positions are rounded to a grid.
+    let g = gcd(from_rate as u64, to_rate as u64);
+    let (mut phases, mut step) = (to_rate as u64 / g, from_rate as u64 / g);
+    let exact = phases as usize <= max_phases;
+    if !exact {
+        phases = max_phases as u64;
+        step = 0;
+    }
+    // Rounding can land on the next input sample, so the grid gets an extra row.
+    let rows = if exact { phases } else { phases + 1 };
+    let table: Vec<Vec<f64>> = (0..rows)
+        .map(|p| weights(p as f64 / phases as f64))
+        .collect();
+
+    let output_len = (input.len() as f64 / ratio).ceil() as usize;
+    let last = input.len() as isize - 1;
+    (0..output_len)
+        .map(|i| {
+            let (base, row) = if exact {
+                let pos = i as u64 * step;
+                ((pos / phases) as isize, (pos % phases) as usize)
+            } else {
+                let pos = i as f64 * ratio;
+                let base = pos.floor();
+                (
+                    base as isize,
+                    ((pos - base) * phases as f64).round() as usize,
+                )
+            };
+            // Past the ends the signal is held at its edge samples.
+            let acc: f64 = table[row]
+                .iter()
+                .zip(base - taps + 1..)
+                .map(|(&w, j)| w * input[j.clamp(0, last) as usize] as f64)
+                .sum();
+            acc as f32
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{interleave, sine, zero_crossing_freq};
+
+    #[test]
+    fn passes_16khz_mono_through() {
+        let tone = sine(440.0, 0.5, 16000);
+        assert_eq!(
+            to_whisper_format(tone.clone(), 16000, 1, ResampleQuality::High),
+            tone
+        );
+    }
+
+    #[test]
+    fn downmixes_stereo_by_averaging() {
+        let stereo = interleave(&[vec![0.5; 100], vec![-0.1; 100]]);
+        let mono = to_whisper_format(stereo, 16000, 2, ResampleQuality::Fast);
+        assert_eq!(mono.len(), 100);
+        assert!(mono.iter().all(|s| (s - 0.2).abs() < 1e-6));
+    }
+
+    #[test]
+    fn buffer_size_must_fit_the_device_range() {
+        let range = SupportedBufferSize::Range { min: 64, max: 4096 };
+        assert_eq!(check_buffer_size(&range, 256), Ok(()));
+        assert_eq!(check_buffer_size(&range, 4096), Ok(()));
+        assert_eq!(
+            check_buffer_size(&range, 8192),
+            Err("buffer size 8192 outside device range 64-4096".to_string())
+        );
+        assert_eq!(
+            check_buffer_size(&SupportedBufferSize::Unknown, 8192),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn resampling_keeps_duration_and_pitch() {
+        // Rates a device may run at with --sample-rate.
+        for rate in [8000, 22050, 44100, 48000] {
+            let out = resample(&sine(1000.0, 1.0, rate), rate, 16000);
+            assert!(
+                out.len().abs_diff(16000) <= 1,
+                "{rate} Hz: {} samples",
+                out.len()
+            );
+            let freq = zero_crossing_freq(&out, 16000);
+            assert!(
+                (freq - 1000.0).abs() < 5.0,
+                "{rate} Hz: tone came out at {freq} Hz"
+            );
+        }
+    }
+
+    #[test]
+    fn every_quality_keeps_duration_and_pitch() {
+        for quality in [
+            ResampleQuality::Fast,
+            ResampleQuality::Balanced,
+            ResampleQuality::High,
+        ] {
+            for rate in [8000, 22050, 44100, 48000] {
+                let tone = sine(1000.0, 1.0, rate);
+                let out = to_whisper_format(tone, rate, 1, quality);
+                assert!(
+                    out.len().abs_diff(16000) <= 1,
+                    "{quality:?} {rate} Hz: {} samples",
+                    out.len()
+                );
+                let freq = zero_crossing_freq(&out, 16000);
+                assert!(
+                    (freq - 1000.0).abs() < 5.0,
+                    "{quality:?} {rate} Hz: tone came out at {freq} Hz"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn resamples_stereo_capture() {
+        let left = sine(440.0, 0.5, 48000);
+        let out = to_whisper_format(
+            interleave(&[left.clone(), left]),
+            48000,
+            2,
+            ResampleQuality::Balanced,
+        );
+        assert!(out.len().abs_diff(8000) <= 1);
+        assert!((zero_crossing_freq(&out, 16000) - 440.0).abs() < 5.0);
+    }
+
+    #[test]
+    fn mix_averages_overlap_and_keeps_longer_tail() {
+        let mixed = mix(vec![vec![1.0, 1.0, 1.0], vec![0.0, 0.0]]);
+        assert_eq!(mixed, vec![0.5, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn mix_of_one_track_is_that_track() {
+        assert_eq!(mix(vec![vec![0.25, -0.5]]), vec![0.25, -0.5]);
+        assert!(mix(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn sinc_resamplers_reject_frequencies_above_16khz_nyquist() {
+        // A 12 kHz tone cannot be represented at 16kHz; linear interpolation
+        // folds it down to an audible 4 kHz alias, the sinc filters remove it.
+        let rms = |s: &[f32]| (s.iter().map(|x| x * x).sum::<f32>() / s.len() as f32).sqrt();
+        let tone = sine(12000.0, 0.5, 48000);
+        let fast = to_whisper_format(tone.clone(), 48000, 1, ResampleQuality::Fast);
+        let balanced = to_whisper_format(tone.clone(), 48000, 1, ResampleQuality::Balanced);
+        let high = to_whisper_format(tone, 48000, 1, ResampleQuality::High);
+        assert!(rms(&fast) > 0.2, "fast: {}", rms(&fast));
+        assert!(rms(&balanced) < 0.02, "balanced: {}", rms(&balanced));
+        assert!(rms(&high) < 0.002, "high: {}", rms(&high));
+    }
+
+    #[test]
+    fn sinc_resamplers_handle_rates_with_too_many_phases() {
+        // 44101 and 16000 share no factor: 16000 phases, more than either table holds.
+        for (zero_crossings, max_phases) in [
+            (BALANCED_ZERO_CROSSINGS, BALANCED_MAX_PHASES),
+            (HIGH_ZERO_CROSSINGS, HIGH_MAX_PHASES),
+        ] {
+            let out = resample_sinc(
+                &sine(1000.0, 1.0, 44101),
+                44101,
+                16000,
+                zero_crossings,
+                max_phases,
+            );
+            assert!(out.len().abs_diff(16000) <= 1);
+            assert!((zero_crossing_freq(&out, 16000) - 1000.0).abs() < 5.0);
+        }
+    }
+
+    #[test]
+    fn sinc_resamplers_keep_dc_level() {
+        for quality in [ResampleQuality::Balanced, ResampleQuality::High] {
+            let out = to_whisper_format(vec![0.25; 4410], 44100, 1, quality);
+            assert!(out.iter().all(|s| (s - 0.25).abs() < 1e-4), "{quality:?}");
+        }
+    }
+
+    #[test]
+    fn remove_rumble_strips_dc_and_low_frequencies() {
+        // A 400 Hz tone has a period of exactly 40 samples at 16kHz, so averaging
+        // 40-sample blocks cancels it and leaves only DC and rumble.
+        let voice = sine(400.0, 1.0, 16000);
+        let rumble = sine(20.0, 1.0, 16000);
+        let mut noisy: Vec<f32> = voice
+            .iter()
+            .zip(&rumble)
+            .map(|(v, r)| v + r + 0.2)
+            .collect();
+        remove_rumble(&mut noisy, 80.0);
+
+        // Skip the filter's settling time.
+        let settled = &noisy[4000..];
+        for block in settled.chunks_exact(40) {
+            let mean = block.iter().sum::<f32>() / 40.0;
+            assert!(mean.abs() < 0.05, "low-frequency content left: {mean}");
+        }
+        assert!((zero_crossing_freq(settled, 16000) - 400.0).abs() < 5.0);
+    }
+
+    #[test]
+    fn highpass_attenuates_below_cutoff_and_passes_speech() {
+        let rms = |s: &[f32]| (s.iter().map(|x| x * x).sum::<f32>() / s.len() as f32).sqrt();
+        let mut low = sine(20.0, 2.0, 16000);
+        highpass(&mut low, 80.0, 16000);
+        assert!(
+            rms(&low[8000..]) < 0.1 * 0.354,
+            "20 Hz: {}",
+            rms(&low[8000..])
+        );
+
+        let mut speech = sine(300.0, 1.0, 16000);
+        highpass(&mut speech, 80.0, 16000);
+        assert!(
+            (rms(&speech[4000..]) - 0.354).abs() < 0.01,
+            "300 Hz: {}",
+            rms(&speech[4000..])
+        );
+    }
+
+    #[test]
+    fn pipe_capture_starts_on_a_frame_boundary() {
+        // Stereo s16le: 4-byte frames, bytes numbered by stream position.
+        let stream: Vec<u8> = (0..64).collect();
+        let mut kept = Vec::new();
+        // Recording starts 7 bytes in, mid-frame; later reads land anywhere.
+        for (start, end) in [(7, 10), (10, 11), (11, 30), (30, 64)] {
+            keep_frames(&mut kept, &stream[start..end], start as u64, 4);
+        }
+        assert_eq!(kept, (8..64).collect::<Vec<u8>>());
+
+        // A read too short to reach the next boundary keeps nothing yet.
+        let mut kept = Vec::new();
+        keep_frames(&mut kept, &stream[5..7], 5, 4);
+        assert!(kept.is_empty());
+        keep_frames(&mut kept, &stream[7..12], 7, 4);
+        assert_eq!(kept, vec![8, 9, 10, 11]);
+    }
+
+    #[test]
+    fn resample_empty_input() {
+        assert!(resample(&[], 44100, 16000).is_empty());
+        assert!(resample_sinc(&[], 44100, 16000, HIGH_ZERO_CROSSINGS, HIGH_MAX_PHASES).is_empty());
+    }
+}
@@ -0,0 +1,196 @@
+//! Transcription backends: local whisper.cpp by default, or an OpenAI/Groq
+//! compatible `/audio/transcriptions` HTTP API, either on its own or as a
+//! fallback for low-confidence local transcripts.
+
+use crate::pcm;
+use crate::transcribe::{self, TranscribeOptions, Transcript};
+use anyhow::{Context, Result, bail};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::Instant;
+use whisper_rs::{WhisperContext, WhisperState};
+
+/// Something that turns 16kHz mono samples into a transcript.
+pub trait TranscriptionBackend {
+    fn transcribe(&mut self, audio: &[f32], opts: &TranscribeOptions) -> Result<Transcript>;
+}
+
+/// Local inference with whisper.cpp. The inference state (KV caches and compute
+/// buffers) is allocated on first use and reused for every later recording.
+pub struct LocalWhisper {
+    ctx: WhisperContext,
+    state: Option<WhisperState>,
+}
+
+impl LocalWhisper {
+    pub fn new(ctx: WhisperContext) -> Self {
+        LocalWhisper { ctx, state: None }
+    }
+}
+
+impl TranscriptionBackend for LocalWhisper {
+    fn transcribe(&mut self, audio: &[f32], opts: &TranscribeOptions) -> Result<Transcript> {
+        let state = match &mut self.state {
+            Some(state) => state,
+            None => {
+                let start = Instant::now();
+                let state = self
+                    .ctx
+                    .create_state()
+                    .context("failed to create whisper state")?;
+                eprintln!(
+                    "[stt-typer] whisper state allocated in {} ms, reused for later recordings",
+                    start.elapsed().as_millis()
+                );
+                self.state.insert(state)
+            }
+        };
+        transcribe::transcribe_with_state(&self.ctx, state, audio, opts)
+    }
+}
+
+/// An OpenAI-compatible transcription API, called through `curl`.
+pub struct RemoteApi {
+    /// Full endpoint URL, e.g. `https://api.openai.com/v1/audio/transcriptions`.
+    pub url: String,
+    /// Model name sent with the request, e.g. `whisper-1`.
+    pub model: String,
+    /// Bearer token, if the API needs one.
+    pub api_key: Option<String>,
+}
+
+impl TranscriptionBackend for RemoteApi {
+    fn transcribe(&mut self, audio: &[f32], opts: &TranscribeOptions) -> Result<Transcript> {
+        // Upload from a temporary file; the API key goes through curl's stdin
+        // config so it never shows up in the process list.
+        let path = write_private_wav(audio)?;
+
+        let mut command = Command::new("curl");
+        command
+            .args(["--silent", "--show-error", "--fail", "--config", "-"])
+            .arg("--form")
+            .arg(format!("file=@{}", path.display()))
+            // --form-string: a leading "@" or "<" in a value is not a file to upload.
+            .arg("--form-string")
+            .arg(format!("model={}", self.model))
+            .args(["--form-string", "response_format=text"]);
+        if opts.language != "auto" {
+            command
+                .arg("--form-string")
+                .arg(format!("language={}", opts.language));
+        }
+        command
+            .arg(&self.url)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        let output = command.spawn().and_then(|mut child| {
+            if let Some(mut stdin) = child.stdin.take()
+                && let Some(key) = &self.api_key
+            {
+                let header = config_quote(&format!("Authorization: Bearer {key}"));
+                writeln!(stdin, "header = {header}")?;
+            }
+            child.wait_with_output()
+        });
+        let _ = std::fs::remove_file(&path);
+
+        let output = output.context("failed to run curl")?;
+        if !output.status.success() {
+            bail!(
+                "remote transcription failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(Transcript {
+            text: String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            language: opts.language.clone(),
+            // The text response format carries no token probabilities.
+            confidence: 1.0,
+            retried: false,
+            tokens: Vec::new(),
+            loop_removed: false,
+            hallucinations_removed: false,
+        })
+    }
+}
+
+/// Quote `value` as a string in a curl config file.
+fn config_quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' | '"' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Write `audio` to a new WAV file only the current user can read, in
+/// `$XDG_RUNTIME_DIR` if set, else the system temp directory. The file is
+/// always created fresh, never opened through an existing path or symlink.
+fn write_private_wav(audio: &[f32]) -> Result<PathBuf> {
+    let dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_dir())
+        .unwrap_or_else(std::env::temp_dir);
+    let wav = pcm::encode_wav(audio, 16000);
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    for attempt in 0..16u32 {
+        let path = dir.join(format!(
+            "stt-typer-{}-{nanos:x}-{attempt}.wav",
+            std::process::id()
+        ));
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path);
+        match file {
+            Ok(mut file) => {
+                if let Err(e) = file.write_all(&wav) {
+                    let _ = std::fs::remove_file(&path);
+                    return Err(e).context("failed to write temporary WAV file");
+                }
+                return Ok(path);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!("failed to create temporary WAV file in {}", dir.display())
+                });
+            }
+        }
+    }
+    bail!(
+        "failed to create a unique temporary WAV file in {}",
+        dir.display()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_quote_escapes_quotes_backslashes_and_newlines() {
+        assert_eq!(config_quote("Bearer sk-abc"), r#""Bearer sk-abc""#);
+        assert_eq!(config_quote(r#"a"b\c"#), r#""a\"b\\c""#);
+        assert_eq!(config_quote("a\nurl = x"), r#""a\nurl = x""#);
+    }
+}
@@ -0,0 +1,178 @@
+//! Casing and punctuation cleanup for short Whisper utterances: drops filler
+//! words, collapses stuttered repeats, sentence-cases the text and makes sure it
+//! ends with terminal punctuation.
+
+/// Filler words removed by default (`--filler-words`).
+pub const DEFAULT_FILLERS: &str = "um,umm,uh,uhm,er,erm,hmm,mm";
+
+/// Clean up a transcript. `fillers` are matched case-insensitively against whole words.
+pub fn cleanup(text: &str, fillers: &[String]) -> String {
+    let words = remove_fillers(text.split_whitespace().collect(), fillers);
+    let words = collapse_repeats(words);
+    let text = sentence_case(&words.join(" "));
+    terminate(text)
+}
+
+/// The word without surrounding punctuation, lowercased.
+fn core(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric() && c != '\'')
+        .to_lowercase()
+}
+
+fn is_terminal(c: char) -> bool {
+    matches!(c, '.' | '?' | '!' | '…')
+}
+
+/// Drop filler words. A sentence-ending mark on a filler ("so um.") moves onto
+/// the preceding word so the sentence boundary survives.
+fn remove_fillers(words: Vec<&str>, fillers: &[String]) -> Vec<String> {
+    let mut out: Vec<String> = Vec::with_capacity(words.len());
+    for word in words {
+        let core = core(word);
+        if !core.is_empty() && fillers.iter().any(|f| f.eq_ignore_ascii_case(&core)) {
+            let terminal = word.chars().rev().find(|&c| is_terminal(c));
+            if let (Some(mark), Some(prev)) = (terminal, out.last_mut()) {
+                let prev_trimmed = prev.trim_end_matches([',', ';']);
+                *prev = format!("{prev_trimmed}{mark}");
+            }
+            continue;
+        }
+        out.push(word.to_string());
+    }
+    out
+}
+
+/// Collapse immediately repeated words ("the the cat" → "the cat"). A repeat
+/// separated by punctuation ("no, no") is kept as deliberate.
+fn collapse_repeats(words: Vec<String>) -> Vec<String> {
+    let mut out: Vec<String> = Vec::with_capacity(words.len());
+    for word in words {
+        if let Some(prev) = out.last_mut() {
+            let prev_bare = prev.ends_with(|c: char| c.is_alphanumeric());
+            if prev_bare && !core(prev).is_empty() && core(prev) == core(&word) {
+                // Keep the later copy's trailing punctuation.
+                *prev = word;
+                continue;
+            }
+        }
+        out.push(word);
+    }
+    out
+}
+
+/// Capitalize the first letter of every sentence and the pronoun "I".
+fn sentence_case(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut sentence_start = true;
+    for (i, word) in text.split(' ').enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        let core = core(word);
+        let word = if sentence_start || core == "i" || core.starts_with("i'") {
+            capitalize_first(word)
+        } else {
+            word.to_string()
+        };
+        sentence_start = word
+            .trim_end_matches(['"', '\'', ')'])
+            .ends_with(is_terminal);
+        out.push_str(&word);
+    }
+    out
+}
+
+fn capitalize_first(word: &str) -> String {
+    let mut out = String::with_capacity(word.len());
+    let mut done = false;
+    for c in word.chars() {
+        if !done && c.is_alphabetic() {
+            out.extend(c.to_uppercase());
+            done = true;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Append a full stop if the text doesn't already end a sentence.
+fn terminate(mut text: String) -> String {
+    let trimmed_len = text.trim_end_matches([',', ';', ':', '-']).len();
+    text.truncate(trimmed_len);
+    let last = text.trim_end_matches(['"', '\'', ')']).chars().last();
+    if last.is_some_and(|c| !is_terminal(c)) {
+        text.push('.');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fillers() -> Vec<String> {
+        DEFAULT_FILLERS.split(',').map(String::from).collect()
+    }
+
+    #[test]
+    fn adds_terminal_punctuation_and_capitalizes() {
+        assert_eq!(cleanup("hello world", &fillers()), "Hello world.");
+        assert_eq!(cleanup("is it done?", &fillers()), "Is it done?");
+    }
+
+    #[test]
+    fn capitalizes_each_sentence_and_pronoun_i() {
+        assert_eq!(
+            cleanup("yes. i think i'm done. ok", &fillers()),
+            "Yes. I think I'm done. Ok."
+        );
+    }
+
+    #[test]
+    fn removes_fillers() {
+        assert_eq!(
+            cleanup("Um, I think uh we should go", &fillers()),
+            "I think we should go."
+        );
+    }
+
+    #[test]
+    fn filler_keeps_sentence_boundary() {
+        assert_eq!(
+            cleanup("that works, um. next one", &fillers()),
+            "That works. Next one."
+        );
+    }
+
+    #[test]
+    fn custom_fillers() {
+        let fillers = vec!["like".to_string()];
+        assert_eq!(
+            cleanup("it was like great um", &fillers),
+            "It was great um."
+        );
+    }
+
+    #[test]
+    fn collapses_repeated_words() {
+        assert_eq!(cleanup("the the cat sat sat.", &fillers()), "The cat sat.");
+        assert_eq!(cleanup("I I I mean it", &fillers()), "I mean it.");
+    }
+
+    #[test]
+    fn keeps_punctuated_repeats() {
+        assert_eq!(cleanup("no, no, no", &fillers()), "No, no, no.");
+    }
+
+    #[test]
+    fn empty_and_filler_only_input() {
+        assert_eq!(cleanup("", &fillers()), "");
+        assert_eq!(cleanup("um uh", &fillers()), "");
+    }
+
+    #[test]
+    fn strips_dangling_comma() {
+        assert_eq!(cleanup("so anyway,", &fillers()), "So anyway.");
+    }
+}
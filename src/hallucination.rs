@@ -0,0 +1,197 @@
+//! Phrases Whisper tends to invent for silence or background noise, learned from
+//! the subtitled video it was trained on. They are only stripped from segments
+//! Whisper itself rates as probably not speech, so a real "thank you" survives.
+
+use crate::words::key;
+
+/// Phrases with at least this many words are stripped wherever they appear in a
+/// segment. Shorter ones ("you", "thank you") are common in real speech, so they
+/// only match a segment made up of nothing but blocked phrases.
+const SUBSTRING_MIN_WORDS: usize = 3;
+
+/// Built-in blocklist for `language`. Phrases are matched case-insensitively on
+/// whole words, ignoring punctuation; a trailing `*` also drops the rest of the
+/// segment and allows the phrase to match anywhere regardless of length.
+pub fn defaults(language: &str) -> &'static [&'static str] {
+    match language {
+        "en" => &[
+            "thanks for watching",
+            "thank you for watching",
+            "thank you so much for watching",
+            "please subscribe",
+            "like and subscribe",
+            "subtitles by *",
+            "transcribed by *",
+            "translated by *",
+            "see you next time",
+            "thank you",
+            "bye",
+            "you",
+        ],
+        "de" => &[
+            "untertitel im auftrag des zdf *",
+            "untertitel von *",
+            "untertitelung *",
+            "vielen dank fürs zuschauen",
+            "danke fürs zuschauen",
+            "bis zum nächsten mal",
+        ],
+        "es" => &[
+            "gracias por ver el video",
+            "gracias por ver",
+            "subtítulos por *",
+            "subtítulos realizados por *",
+            "suscríbete",
+            "gracias",
+        ],
+        "fr" => &[
+            "sous-titres réalisés par *",
+            "sous-titrage *",
+            "merci d'avoir regardé",
+            "abonnez-vous",
+            "merci",
+        ],
+        "it" => &[
+            "sottotitoli creati dalla comunità *",
+            "sottotitoli *",
+            "grazie per la visione",
+            "iscriviti al canale",
+            "grazie",
+        ],
+        "pt" => &[
+            "legendas pela comunidade *",
+            "obrigado por assistir",
+            "inscreva-se",
+            "obrigado",
+        ],
+        "nl" => &["ondertiteling *", "bedankt voor het kijken", "bedankt"],
+        _ => &[],
+    }
+}
+
+/// Parse a blocklist file: one phrase per line, blank lines and `#` comments ignored.
+pub fn parse_list(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+/// Remove blocked phrases from `text`: long phrases and `*` forms wherever they
+/// occur, short phrases only if they are all that is left of the segment.
+/// Returns `None` if nothing matched.
+pub fn strip<S: AsRef<str>>(text: &str, phrases: &[S]) -> Option<String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let keys: Vec<String> = words.iter().map(|w| key(w)).collect();
+    let mut phrases: Vec<(Vec<String>, bool)> = phrases
+        .iter()
+        .filter_map(|phrase| {
+            let phrase = phrase.as_ref().trim();
+            let (phrase, rest) = match phrase.strip_suffix('*') {
+                Some(p) => (p, true),
+                None => (phrase, false),
+            };
+            let keys: Vec<String> = phrase
+                .split_whitespace()
+                .map(key)
+                .filter(|k| !k.is_empty())
+                .collect();
+            (!keys.is_empty()).then_some((keys, rest))
+        })
+        .collect();
+    // Longest first, so "thank you for watching" wins over "thank you".
+    phrases.sort_by_key(|(keys, _)| std::cmp::Reverse(keys.len()));
+
+    let mut out: Vec<usize> = Vec::with_capacity(words.len());
+    let mut changed = false;
+    let mut i = 0;
+    'words: while i < words.len() {
+        for (phrase, rest) in &phrases {
+            if (*rest || phrase.len() >= SUBSTRING_MIN_WORDS) && keys[i..].starts_with(phrase) {
+                i = if *rest { words.len() } else { i + phrase.len() };
+                changed = true;
+                continue 'words;
+            }
+        }
+        out.push(i);
+        i += 1;
+    }
+
+    // What remains may still be nothing but short phrases, e.g. "Thank you. Bye."
+    let remaining: Vec<&str> = out
+        .iter()
+        .map(|&i| keys[i].as_str())
+        .filter(|k| !k.is_empty())
+        .collect();
+    if !remaining.is_empty() && only_phrases(&remaining, &phrases) {
+        return Some(String::new());
+    }
+
+    changed.then(|| out.iter().map(|&i| words[i]).collect::<Vec<_>>().join(" "))
+}
+
+/// Whether `keys` is entirely a sequence of blocked phrases.
+fn only_phrases(keys: &[&str], phrases: &[(Vec<String>, bool)]) -> bool {
+    keys.is_empty()
+        || phrases.iter().any(|(phrase, _)| {
+            keys.len() >= phrase.len()
+                && keys.iter().zip(phrase).all(|(k, p)| k == p)
+                && only_phrases(&keys[phrase.len()..], phrases)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_short_phrases_inside_real_speech() {
+        let en = defaults("en");
+        assert_eq!(strip(" Can you hear me?", en), None);
+        assert_eq!(strip(" I'll see you tomorrow, thank you.", en), None);
+        assert_eq!(strip(" Merci pour tout.", defaults("fr")), None);
+    }
+
+    #[test]
+    fn drops_segments_made_only_of_short_phrases() {
+        let en = defaults("en");
+        assert_eq!(strip(" Thank you.", en).as_deref(), Some(""));
+        assert_eq!(strip(" you", en).as_deref(), Some(""));
+        assert_eq!(strip(" Thank you. Bye!", en).as_deref(), Some(""));
+        assert_eq!(strip(" Gracias.", defaults("es")).as_deref(), Some(""));
+    }
+
+    #[test]
+    fn strips_long_phrases_anywhere() {
+        let en = defaults("en");
+        assert_eq!(
+            strip(" Let's start. Thanks for watching!", en).as_deref(),
+            Some("Let's start.")
+        );
+        // The long phrase goes first, and the short leftover then fills the segment.
+        assert_eq!(
+            strip(" Thank you for watching. Bye.", en).as_deref(),
+            Some("")
+        );
+    }
+
+    #[test]
+    fn star_drops_rest_of_segment() {
+        let en = defaults("en");
+        assert_eq!(
+            strip(" Okay. Subtitles by the Amara.org community", en).as_deref(),
+            Some("Okay.")
+        );
+        let custom = ["um *"];
+        assert_eq!(strip(" so um whatever", &custom).as_deref(), Some("so"));
+    }
+
+    #[test]
+    fn parses_list_files() {
+        let list = parse_list("# comment\n\n  thanks for listening  \nsubtitles by *\n");
+        assert_eq!(list, vec!["thanks for listening", "subtitles by *"]);
+        assert_eq!(strip(" Thanks for listening.", &list).as_deref(), Some(""));
+    }
+}
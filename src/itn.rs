@@ -0,0 +1,1065 @@
+//! Inverse text normalization: rewrite spoken-form numbers and dates as written
+//! digits, e.g. "twenty third of march two thousand twenty five" → "23 March 2025"
+//! and "version one point two" → "version 1.2".
+//!
+//! The transcript is split into word tokens and, at each position, the rules in
+//! [`RULES`] are tried in order. The first rule that matches replaces the tokens
+//! it consumed; otherwise the token is copied through unchanged.
+//!
+//! Spoken forms are recognized in English only; the written forms (decimal and
+//! thousands separators, ordinals, percent sign, month names and date order)
+//! follow a [`Locale`].
+
+use std::str::FromStr;
+
+const UNITS: [&str; 20] = [
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+
+const TENS: [&str; 8] = [
+    "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+
+const UNIT_ORDINALS: [&str; 20] = [
+    "zeroth",
+    "first",
+    "second",
+    "third",
+    "fourth",
+    "fifth",
+    "sixth",
+    "seventh",
+    "eighth",
+    "ninth",
+    "tenth",
+    "eleventh",
+    "twelfth",
+    "thirteenth",
+    "fourteenth",
+    "fifteenth",
+    "sixteenth",
+    "seventeenth",
+    "eighteenth",
+    "nineteenth",
+];
+
+const TENS_ORDINALS: [&str; 8] = [
+    "twentieth",
+    "thirtieth",
+    "fortieth",
+    "fiftieth",
+    "sixtieth",
+    "seventieth",
+    "eightieth",
+    "ninetieth",
+];
+
+/// Plural decades and centuries that can follow a century ("the nineteen eighties").
+const DECADES: [&str; 9] = [
+    "twenties",
+    "thirties",
+    "forties",
+    "fifties",
+    "sixties",
+    "seventies",
+    "eighties",
+    "nineties",
+    "hundreds",
+];
+
+const SCALES: [(&str, &str, u64); 3] = [
+    ("thousand", "thousandth", 1_000),
+    ("million", "millionth", 1_000_000),
+    ("billion", "billionth", 1_000_000_000),
+];
+
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+/// Written conventions for numbers and dates.
+#[derive(Clone, Copy)]
+pub struct Locale {
+    decimal: char,
+    /// Thousands separator, used from five digits up.
+    group: char,
+    /// Written after the number: "%" or " %".
+    percent: &'static str,
+    ordinal: Ordinal,
+    months: [&'static str; 12],
+    /// Day-first date separators: after the day and before the year
+    /// ("23. März 2025", "23 de marzo de 2025"). `None` keeps the spoken order,
+    /// as in English "March 23, 2025" and "23 March 2025".
+    day_first: Option<(&'static str, &'static str)>,
+    /// How the first of the month is written in a date ("1er mars").
+    day_one: &'static str,
+}
+
+#[derive(Clone, Copy)]
+enum Ordinal {
+    /// "1st", "2nd", "23rd"
+    English,
+    /// Appended to the digits: "23." (de), "23e" (nl, fr), "23º" (es, it, pt).
+    Suffix(&'static str),
+    /// French: "1er", then "2e", "23e".
+    French,
+}
+
+const ENGLISH_MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const EN: Locale = Locale {
+    decimal: '.',
+    group: ',',
+    percent: "%",
+    ordinal: Ordinal::English,
+    months: ENGLISH_MONTHS,
+    day_first: None,
+    day_one: "1",
+};
+
+const EN_GB: Locale = Locale {
+    day_first: Some((" ", " ")),
+    ..EN
+};
+
+const DE: Locale = Locale {
+    decimal: ',',
+    group: '.',
+    percent: " %",
+    ordinal: Ordinal::Suffix("."),
+    months: [
+        "Januar",
+        "Februar",
+        "März",
+        "April",
+        "Mai",
+        "Juni",
+        "Juli",
+        "August",
+        "September",
+        "Oktober",
+        "November",
+        "Dezember",
+    ],
+    day_first: Some((". ", " ")),
+    day_one: "1",
+};
+
+const FR: Locale = Locale {
+    decimal: ',',
+    group: ' ',
+    percent: " %",
+    ordinal: Ordinal::French,
+    months: [
+        "janvier",
+        "février",
+        "mars",
+        "avril",
+        "mai",
+        "juin",
+        "juillet",
+        "août",
+        "septembre",
+        "octobre",
+        "novembre",
+        "décembre",
+    ],
+    day_first: Some((" ", " ")),
+    day_one: "1er",
+};
+
+const ES: Locale = Locale {
+    decimal: ',',
+    group: '.',
+    percent: " %",
+    ordinal: Ordinal::Suffix("º"),
+    months: [
+        "enero",
+        "febrero",
+        "marzo",
+        "abril",
+        "mayo",
+        "junio",
+        "julio",
+        "agosto",
+        "septiembre",
+        "octubre",
+        "noviembre",
+        "diciembre",
+    ],
+    day_first: Some((" de ", " de ")),
+    day_one: "1",
+};
+
+const IT: Locale = Locale {
+    decimal: ',',
+    group: '.',
+    percent: "%",
+    ordinal: Ordinal::Suffix("º"),
+    months: [
+        "gennaio",
+        "febbraio",
+        "marzo",
+        "aprile",
+        "maggio",
+        "giugno",
+        "luglio",
+        "agosto",
+        "settembre",
+        "ottobre",
+        "novembre",
+        "dicembre",
+    ],
+    day_first: Some((" ", " ")),
+    day_one: "1º",
+};
+
+const NL: Locale = Locale {
+    decimal: ',',
+    group: '.',
+    percent: "%",
+    ordinal: Ordinal::Suffix("e"),
+    months: [
+        "januari",
+        "februari",
+        "maart",
+        "april",
+        "mei",
+        "juni",
+        "juli",
+        "augustus",
+        "september",
+        "oktober",
+        "november",
+        "december",
+    ],
+    day_first: Some((" ", " ")),
+    day_one: "1",
+};
+
+const PT: Locale = Locale {
+    decimal: ',',
+    group: '.',
+    percent: "%",
+    ordinal: Ordinal::Suffix("º"),
+    months: [
+        "janeiro",
+        "fevereiro",
+        "março",
+        "abril",
+        "maio",
+        "junho",
+        "julho",
+        "agosto",
+        "setembro",
+        "outubro",
+        "novembro",
+        "dezembro",
+    ],
+    day_first: Some((" de ", " de ")),
+    day_one: "1º",
+};
+
+impl Locale {
+    /// The conventions for a Whisper language code, falling back to English.
+    pub fn for_language(language: &str) -> Locale {
+        language.parse().unwrap_or(EN)
+    }
+
+    fn ordinal(&self, value: u64) -> String {
+        match self.ordinal {
+            Ordinal::English => format!("{value}{}", ordinal_suffix(value)),
+            Ordinal::Suffix(suffix) => format!("{value}{suffix}"),
+            Ordinal::French if value == 1 => "1er".to_string(),
+            Ordinal::French => format!("{value}e"),
+        }
+    }
+
+    /// A date with an optional day and year, in the order it was spoken unless
+    /// the locale writes dates day first.
+    fn date(
+        &self,
+        day: Option<u64>,
+        month: usize,
+        year: Option<u64>,
+        spoken_day_first: bool,
+    ) -> String {
+        let month = self.months[month];
+        let mut out = match (day, self.day_first) {
+            (None, _) => month.to_string(),
+            (Some(1), Some((after_day, _))) => format!("{}{after_day}{month}", self.day_one),
+            (Some(day), Some((after_day, _))) => format!("{day}{after_day}{month}"),
+            (Some(day), None) if spoken_day_first => format!("{day} {month}"),
+            (Some(day), None) => format!("{month} {day}"),
+        };
+        if let Some(year) = year {
+            let before_year = match (day, self.day_first) {
+                (_, Some((_, before_year))) => before_year,
+                (Some(_), None) if !spoken_day_first => ", ",
+                _ => " ",
+            };
+            out.push_str(&format!("{before_year}{year}"));
+        }
+        out
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('_', "-").as_str() {
+            "en" | "en-us" => Ok(EN),
+            "en-gb" => Ok(EN_GB),
+            "de" => Ok(DE),
+            "fr" => Ok(FR),
+            "es" => Ok(ES),
+            "it" => Ok(IT),
+            "nl" => Ok(NL),
+            "pt" => Ok(PT),
+            _ => Err(format!(
+                "unsupported number locale \"{s}\" (expected en, en-gb, de, fr, es, it, nl or pt)"
+            )),
+        }
+    }
+}
+
+/// A transcript rule: given the tokens starting at the current position, return
+/// how many tokens it consumed and the written form to replace them with.
+type Rule = fn(&[Token], &Locale) -> Option<(usize, String)>;
+
+/// Tried in order; [`number_rule`] runs last and only when none of these match.
+const RULES: [Rule; 4] = [day_of_month_rule, month_rule, year_rule, percent_rule];
+
+/// Rewrite spoken numbers, ordinals, decimals, percentages, years and dates in
+/// `text`, written the way `locale` writes them.
+pub fn normalize(text: &str, locale: &Locale) -> String {
+    let tokens = tokenize(text);
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    // Version numbers use dots in every locale ("version 1.2", not "version 1,2").
+    let dotted = Locale {
+        decimal: '.',
+        ..*locale
+    };
+
+    while i < tokens.len() {
+        // A number right after a digit word left as prose belongs to the same
+        // spoken sequence ("nine eleven", "five twenty"), so it stays prose too.
+        let after_digit = i > 0 && is_spoken_digit(&tokens[i - 1]);
+        let after_version = i > 0 && tokens[i - 1].word == "version" && tokens[i - 1].joins_next();
+        let locale = if after_version { &dotted } else { locale };
+        let matched = RULES
+            .iter()
+            .find_map(|rule| rule(&tokens[i..], locale))
+            .or_else(|| {
+                (!after_digit)
+                    .then(|| number_rule(&tokens[i..], locale))
+                    .flatten()
+            });
+        match matched {
+            Some((len, written)) => {
+                let first = &tokens[i];
+                let last = &tokens[i + len - 1];
+                out.push_str(first.sep);
+                out.push_str(first.lead);
+                out.push_str(&written);
+                out.push_str(last.trail);
+                i += len;
+            }
+            None => {
+                out.push_str(tokens[i].sep);
+                out.push_str(tokens[i].raw);
+                i += 1;
+            }
+        }
+    }
+
+    out
+}
+
+/// Whether `token` is a cardinal digit word ("nine") joined to the next token.
+/// Only called on tokens that were left unchanged, since a matched token is
+/// never looked at again.
+fn is_spoken_digit(token: &Token) -> bool {
+    token.joins_next() && matches!(classify(&token.word), Some((Word::Unit(1..=9), false)))
+}
+
+struct Token<'a> {
+    /// Separator preceding this token in the original text ("", " " or "-").
+    sep: &'a str,
+    /// The token as written, including any punctuation.
+    raw: &'a str,
+    /// Punctuation before the word, e.g. an opening quote.
+    lead: &'a str,
+    /// Punctuation after the word, e.g. a comma or full stop.
+    trail: &'a str,
+    /// Lowercased word with punctuation stripped.
+    word: String,
+}
+
+impl Token<'_> {
+    /// Whether a multi-token match may continue past this token.
+    fn joins_next(&self) -> bool {
+        self.trail.is_empty()
+    }
+
+    /// Whether the word starts with a capital letter.
+    fn capitalized(&self) -> bool {
+        self.raw[self.lead.len()..].starts_with(char::is_uppercase)
+    }
+}
+
+fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    for (n, chunk) in text.split_whitespace().enumerate() {
+        // Split hyphenated number words ("twenty-three") into separate tokens so the
+        // rules see them individually, keeping the hyphen for unmatched output.
+        let pieces: Vec<&str> = chunk.split('-').collect();
+        let hyphenated = pieces.len() > 1
+            && pieces.iter().all(|p| {
+                is_number_word(
+                    &p.trim_matches(|c: char| !c.is_alphanumeric())
+                        .to_lowercase(),
+                )
+            });
+        let pieces = if hyphenated { pieces } else { vec![chunk] };
+
+        for (k, piece) in pieces.iter().enumerate() {
+            let sep = match (n, k) {
+                (0, 0) => "",
+                (_, 0) => " ",
+                _ => "-",
+            };
+            let start = piece
+                .find(|c: char| c.is_alphanumeric())
+                .unwrap_or(piece.len());
+            let end = piece
+                .rfind(|c: char| c.is_alphanumeric())
+                .map_or(start, |i| {
+                    i + piece[i..].chars().next().map_or(1, char::len_utf8)
+                });
+            tokens.push(Token {
+                sep,
+                raw: piece,
+                lead: &piece[..start],
+                trail: &piece[end..],
+                word: piece[start..end].to_lowercase(),
+            });
+        }
+    }
+    tokens
+}
+
+fn is_number_word(word: &str) -> bool {
+    UNITS.contains(&word)
+        || TENS.contains(&word)
+        || UNIT_ORDINALS.contains(&word)
+        || TENS_ORDINALS.contains(&word)
+        || matches!(word, "hundred" | "hundredth")
+        || SCALES.iter().any(|(c, o, _)| word == *c || word == *o)
+}
+
+/// A parsed spoken number.
+struct Number {
+    value: u64,
+    /// Number of tokens consumed.
+    len: usize,
+    /// Whether the number ended in an ordinal word ("twenty third").
+    ordinal: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Last {
+    None,
+    Unit,
+    Teen,
+    Tens,
+    Hundred,
+    Scale,
+}
+
+enum Word {
+    Unit(u64),
+    Tens(u64),
+    Hundred,
+    Scale(u64),
+}
+
+/// Classify a number word, returning the word and whether it was an ordinal form.
+fn classify(word: &str) -> Option<(Word, bool)> {
+    if let Some(v) = UNITS.iter().position(|w| *w == word) {
+        return Some((Word::Unit(v as u64), false));
+    }
+    if let Some(v) = UNIT_ORDINALS.iter().position(|w| *w == word) {
+        return Some((Word::Unit(v as u64), true));
+    }
+    if let Some(v) = TENS.iter().position(|w| *w == word) {
+        return Some((Word::Tens(20 + 10 * v as u64), false));
+    }
+    if let Some(v) = TENS_ORDINALS.iter().position(|w| *w == word) {
+        return Some((Word::Tens(20 + 10 * v as u64), true));
+    }
+    match word {
+        "hundred" => return Some((Word::Hundred, false)),
+        "hundredth" => return Some((Word::Hundred, true)),
+        _ => {}
+    }
+    SCALES.iter().find_map(|(cardinal, ordinal, scale)| {
+        if word == *cardinal {
+            Some((Word::Scale(*scale), false))
+        } else if word == *ordinal {
+            Some((Word::Scale(*scale), true))
+        } else {
+            None
+        }
+    })
+}
+
+/// Parse the longest well-formed spoken cardinal or ordinal at the start of `tokens`.
+///
+/// Sequences that don't form a single number ("one two three") stop at the first
+/// word that can't follow the previous one.
+fn parse_number(tokens: &[Token]) -> Option<Number> {
+    let mut total = 0u64;
+    let mut current = 0u64;
+    let mut last = Last::None;
+    let mut last_scale = u64::MAX;
+    let mut len = 0;
+    let mut ordinal = false;
+
+    while let Some(token) = tokens.get(len) {
+        if len > 0 && (!tokens[len - 1].joins_next() || !token.lead.is_empty()) {
+            break;
+        }
+
+        // "one hundred and five": only consume "and" when a number follows it.
+        if token.word == "and" && matches!(last, Last::Hundred | Last::Scale) {
+            let continues = tokens.get(len + 1).is_some_and(|next| {
+                token.joins_next()
+                    && next.lead.is_empty()
+                    && matches!(
+                        classify(&next.word),
+                        Some((Word::Unit(1..), _) | (Word::Tens(_), _))
+                    )
+            });
+            if !continues {
+                break;
+            }
+            len += 1;
+            continue;
+        }
+
+        let Some((word, is_ordinal)) = classify(&token.word) else {
+            break;
+        };
+
+        match word {
+            Word::Unit(0) => {
+                if last != Last::None {
+                    break;
+                }
+                // "zero" is only ever a number on its own.
+                len += 1;
+                ordinal = is_ordinal;
+                break;
+            }
+            Word::Unit(v) if v < 10 => {
+                if !matches!(last, Last::None | Last::Tens | Last::Hundred | Last::Scale) {
+                    break;
+                }
+                current += v;
+                last = Last::Unit;
+            }
+            Word::Unit(v) => {
+                if !matches!(last, Last::None | Last::Hundred | Last::Scale) {
+                    break;
+                }
+                current += v;
+                last = Last::Teen;
+            }
+            Word::Tens(v) => {
+                if !matches!(last, Last::None | Last::Hundred | Last::Scale) {
+                    break;
+                }
+                current += v;
+                last = Last::Tens;
+            }
+            Word::Hundred => {
+                if !matches!(last, Last::Unit | Last::Teen | Last::Tens) || current >= 100 {
+                    break;
+                }
+                current *= 100;
+                last = Last::Hundred;
+            }
+            Word::Scale(scale) => {
+                if matches!(last, Last::None | Last::Scale) || scale >= last_scale {
+                    break;
+                }
+                total += current * scale;
+                current = 0;
+                last = Last::Scale;
+                last_scale = scale;
+            }
+        }
+
+        len += 1;
+        if is_ordinal {
+            ordinal = true;
+            break;
+        }
+    }
+
+    // Never end on a dangling "and".
+    while len > 0 && tokens[len - 1].word == "and" {
+        len -= 1;
+    }
+    (len > 0).then_some(Number {
+        value: total + current,
+        len,
+        ordinal,
+    })
+}
+
+/// Parse a two-digit group of a spoken year: "nineteen", "ninety nine", "oh five".
+fn parse_year_pair(tokens: &[Token]) -> Option<(u64, usize)> {
+    let first = tokens.first()?;
+    if first.word == "oh" {
+        let unit = tokens.get(1).filter(|_| first.joins_next())?;
+        return match classify(&unit.word)? {
+            (Word::Unit(v), false) if (1..10).contains(&v) => Some((v, 2)),
+            _ => None,
+        };
+    }
+    match classify(&first.word)? {
+        (Word::Unit(v), false) if v >= 10 => Some((v, 1)),
+        (Word::Tens(v), false) => {
+            let unit = tokens
+                .get(1)
+                .filter(|t| first.joins_next() && t.lead.is_empty());
+            match unit.and_then(|t| classify(&t.word)) {
+                Some((Word::Unit(u), false)) if (1..10).contains(&u) => Some((v + u, 2)),
+                _ => Some((v, 1)),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Parse a spoken year either in pairs ("nineteen ninety nine", "twenty twenty five")
+/// or as a cardinal ("two thousand and five").
+fn parse_year(tokens: &[Token]) -> Option<(u64, usize)> {
+    if let Some((century, n)) = parse_year_pair(tokens)
+        && (11..=20).contains(&century)
+        && n == 1
+        && tokens[0].joins_next()
+        && let Some(rest) = tokens.get(1..)
+        && let Some((year, m)) = parse_year_pair(rest)
+    {
+        return Some((century * 100 + year, n + m));
+    }
+    match parse_number(tokens)? {
+        Number {
+            value,
+            len,
+            ordinal: false,
+        } if len > 1 && (1000..=2999).contains(&value) => Some((value, len)),
+        _ => None,
+    }
+}
+
+/// Parse a day of the month: "twenty third", "the fifth", "twelve". The day's
+/// `len` includes a leading "the".
+fn parse_day(tokens: &[Token]) -> Option<Number> {
+    let skip = usize::from(tokens.first()?.word == "the" && tokens[0].joins_next());
+    let number = parse_number(&tokens[skip..])?;
+    (1..=31).contains(&number.value).then_some(Number {
+        len: skip + number.len,
+        ..number
+    })
+}
+
+/// The month's index (0 = January).
+fn parse_month(token: &Token) -> Option<usize> {
+    let index = MONTHS.iter().position(|m| *m == token.word)?;
+    // "may" is far more often a verb; only treat it as a month when capitalized.
+    if token.word == "may" && !token.capitalized() {
+        return None;
+    }
+    Some(index)
+}
+
+/// Optional year following a date, returning its written form and length.
+fn trailing_year(tokens: &[Token], after: usize) -> Option<(u64, usize)> {
+    if after == 0 || !tokens[after - 1].joins_next() && tokens[after - 1].trail != "," {
+        return None;
+    }
+    let rest = tokens.get(after..)?;
+    if !rest.first()?.lead.is_empty() {
+        return None;
+    }
+    parse_year(rest)
+}
+
+/// "[the] twenty third of march [two thousand twenty five]" → "23 March 2025".
+fn day_of_month_rule(tokens: &[Token], locale: &Locale) -> Option<(usize, String)> {
+    let Number {
+        value: day, len: n, ..
+    } = parse_day(tokens)?;
+    let of = tokens
+        .get(n)
+        .filter(|t| t.word == "of" && tokens[n - 1].joins_next())?;
+    let month = tokens
+        .get(n + 1)
+        .filter(|_| of.joins_next())
+        .and_then(parse_month)?;
+    let mut len = n + 2;
+    let year = trailing_year(tokens, len).map(|(year, m)| {
+        len += m;
+        year
+    });
+    Some((len, locale.date(Some(day), month, year, true)))
+}
+
+/// "march twenty third [twenty twenty five]" → "March 23, 2025", or "march twenty
+/// twenty five" → "March 2025".
+fn month_rule(tokens: &[Token], locale: &Locale) -> Option<(usize, String)> {
+    let month = parse_month(tokens.first()?)?;
+    if !tokens[0].joins_next() {
+        return None;
+    }
+    let rest = tokens.get(1..)?;
+    if let Some((year, n)) = parse_year(rest)
+        && n > 1
+    {
+        return Some((1 + n, locale.date(None, month, Some(year), false)));
+    }
+    let day = parse_day(rest)?;
+    let mut len = 1 + day.len;
+    let year = trailing_year(tokens, len).map(|(year, m)| {
+        len += m;
+        year
+    });
+    // "march" is also a verb ("we march ten miles"): lowercase, it is only a month
+    // before an ordinal day or a year.
+    if tokens[0].word == "march" && !tokens[0].capitalized() && !day.ordinal && year.is_none() {
+        return None;
+    }
+    Some((len, locale.date(Some(day.value), month, year, false)))
+}
+
+/// "nineteen ninety nine" → "1999" outside of a date.
+fn year_rule(tokens: &[Token], _locale: &Locale) -> Option<(usize, String)> {
+    let (century, n) = parse_year_pair(tokens)?;
+    if n != 1 || !(11..=20).contains(&century) || !tokens[0].joins_next() {
+        return None;
+    }
+    let (year, m) = parse_year_pair(&tokens[1..])?;
+    // Two teens in a row ("fifteen sixteen") is a list, not a year.
+    if tokens[1].word != "oh" && year < 20 {
+        return None;
+    }
+    Some((n + m, format!("{}", century * 100 + year)))
+}
+
+/// "five percent" → "5%", "two point five percent" → "2.5%".
+fn percent_rule(tokens: &[Token], locale: &Locale) -> Option<(usize, String)> {
+    let numeric = parse_numeric(tokens, locale)?;
+    let len = numeric.len;
+    let percent = tokens
+        .get(len)
+        .filter(|t| t.word == "percent" && tokens[len - 1].joins_next())?;
+    if !percent.lead.is_empty() {
+        return None;
+    }
+    Some((len + 1, format!("{}{}", numeric.written, locale.percent)))
+}
+
+/// Cardinals, ordinals and decimals: "one hundred and five" → "105",
+/// "twenty third" → "23rd", "one point two" → "1.2".
+///
+/// A lone single-digit word ("one", "first") is left as prose.
+fn number_rule(tokens: &[Token], locale: &Locale) -> Option<(usize, String)> {
+    let numeric = parse_numeric(tokens, locale)?;
+    if numeric.lone_digit {
+        return None;
+    }
+    // "the nineteen eighties": a century naming a decade, not the number 19.
+    let decade = numeric.len == 1
+        && tokens[0].joins_next()
+        && tokens
+            .get(1)
+            .is_some_and(|t| DECADES.contains(&t.word.as_str()))
+        && matches!(classify(&tokens[0].word), Some((Word::Unit(10..), false)));
+    (!decade).then_some((numeric.len, numeric.written))
+}
+
+/// A cardinal, ordinal or decimal in written form.
+struct Numeric {
+    len: usize,
+    written: String,
+    /// A single word below ten, which usually reads better spelled out.
+    lone_digit: bool,
+}
+
+/// Parse a cardinal, ordinal or decimal and format it.
+fn parse_numeric(tokens: &[Token], locale: &Locale) -> Option<Numeric> {
+    let number = parse_number(tokens)?;
+    let lone_digit = number.len == 1 && number.value < 10;
+    if number.ordinal {
+        return Some(Numeric {
+            len: number.len,
+            written: locale.ordinal(number.value),
+            lone_digit,
+        });
+    }
+
+    // Decimal part: "point" followed by single digits ("point two five"). Further
+    // "point" groups make it a version number ("one point two point three").
+    let mut len = number.len;
+    let mut groups = Vec::new();
+    while let Some((digits, end)) = point_digits(tokens, len) {
+        groups.push(digits);
+        len = end;
+    }
+    match groups.as_slice() {
+        [] => {}
+        [fraction] => {
+            return Some(Numeric {
+                len,
+                written: format!(
+                    "{}{}{fraction}",
+                    format_integer(number.value, locale),
+                    locale.decimal
+                ),
+                lone_digit: false,
+            });
+        }
+        _ => {
+            return Some(Numeric {
+                len,
+                written: format!("{}.{}", number.value, groups.join(".")),
+                lone_digit: false,
+            });
+        }
+    }
+
+    Some(Numeric {
+        len: number.len,
+        written: format_integer(number.value, locale),
+        lone_digit,
+    })
+}
+
+/// A "point" at `start` followed by single spoken digits, returned as a digit
+/// string with the index just past the last digit.
+fn point_digits(tokens: &[Token], start: usize) -> Option<(String, usize)> {
+    if !tokens[start - 1].joins_next()
+        || !tokens
+            .get(start)
+            .is_some_and(|t| t.word == "point" && t.lead.is_empty())
+    {
+        return None;
+    }
+    let mut digits = String::new();
+    let mut k = start + 1;
+    while let Some(token) = tokens.get(k) {
+        if !tokens[k - 1].joins_next() || !token.lead.is_empty() {
+            break;
+        }
+        let digit = match classify(&token.word) {
+            Some((Word::Unit(v), false)) if v < 10 => v,
+            _ if token.word == "oh" => 0,
+            _ => break,
+        };
+        digits.push(char::from(b'0' + digit as u8));
+        k += 1;
+    }
+    (!digits.is_empty()).then_some((digits, k))
+}
+
+fn ordinal_suffix(value: u64) -> &'static str {
+    match (value % 10, value % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    }
+}
+
+/// Format an integer, grouping thousands from five digits up.
+fn format_integer(value: u64, locale: &Locale) -> String {
+    let digits = value.to_string();
+    if digits.len() < 5 {
+        return digits;
+    }
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(locale.group);
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn en(text: &str) -> String {
+        normalize(text, &EN)
+    }
+
+    #[test]
+    fn cardinals_ordinals_and_decimals() {
+        assert_eq!(en("one hundred and five people"), "105 people");
+        assert_eq!(en("twelve thousand three hundred forty five"), "12,345");
+        assert_eq!(en("the twenty first place"), "the 21st place");
+        assert_eq!(en("version one point two"), "version 1.2");
+        assert_eq!(en("twenty-three"), "23");
+    }
+
+    #[test]
+    fn leaves_lone_digits_and_digit_lists() {
+        assert_eq!(en("one more thing"), "one more thing");
+        assert_eq!(en("the first step"), "the first step");
+        assert_eq!(en("one two three"), "one two three");
+        assert_eq!(en("nine eleven"), "nine eleven");
+        assert_eq!(en("at five twenty five"), "at five twenty five");
+        assert_eq!(en("one, twenty"), "one, 20");
+    }
+
+    #[test]
+    fn decades_stay_words() {
+        assert_eq!(en("the nineteen eighties"), "the nineteen eighties");
+        assert_eq!(en("the eighteen hundreds"), "the eighteen hundreds");
+        assert_eq!(en("nineteen eighty"), "1980");
+        assert_eq!(en("nineteen people"), "19 people");
+    }
+
+    #[test]
+    fn version_numbers() {
+        assert_eq!(en("version one point two point three"), "version 1.2.3");
+        assert_eq!(
+            en("upgrade to two point oh point one."),
+            "upgrade to 2.0.1."
+        );
+        let de: Locale = "de".parse().unwrap();
+        assert_eq!(
+            normalize("version one point two point three", &de),
+            "version 1.2.3"
+        );
+        assert_eq!(normalize("version one point two", &de), "version 1.2");
+        assert_eq!(normalize("one point five", &de), "1,5");
+    }
+
+    #[test]
+    fn dates_and_years() {
+        assert_eq!(
+            en("the twenty third of march two thousand twenty five"),
+            "23 March 2025"
+        );
+        assert_eq!(
+            en("march twenty third twenty twenty five"),
+            "March 23, 2025"
+        );
+        assert_eq!(en("in nineteen ninety nine"), "in 1999");
+        assert_eq!(en("twenty oh five"), "2005");
+    }
+
+    #[test]
+    fn may_needs_capital_to_be_a_month() {
+        assert_eq!(en("you may fifth"), "you may fifth");
+        assert_eq!(en("on May fifth"), "on May 5");
+    }
+
+    #[test]
+    fn lowercase_march_before_a_cardinal_is_a_verb() {
+        assert_eq!(en("we march ten miles"), "we march 10 miles");
+        assert_eq!(en("on March ten"), "on March 10");
+        assert_eq!(en("march ten twenty twenty five"), "March 10, 2025");
+        assert_eq!(en("the tenth of march"), "10 March");
+    }
+
+    #[test]
+    fn percentages_and_punctuation() {
+        assert_eq!(en("five percent."), "5%.");
+        assert_eq!(en("two point five percent"), "2.5%");
+        assert_eq!(en("twenty, thirty"), "20, 30");
+    }
+
+    #[test]
+    fn locale_formats() {
+        let de: Locale = "de".parse().unwrap();
+        assert_eq!(
+            normalize("twelve thousand three hundred forty five point five", &de),
+            "12.345,5"
+        );
+        assert_eq!(normalize("the first of june", &de), "1. Juni");
+        assert_eq!(normalize("five percent", &de), "5 %");
+
+        let fr: Locale = "fr".parse().unwrap();
+        assert_eq!(normalize("the first of june", &fr), "1er juin");
+
+        let es: Locale = "es".parse().unwrap();
+        assert_eq!(
+            normalize("march twenty third twenty twenty five", &es),
+            "23 de marzo de 2025"
+        );
+
+        let gb: Locale = "en-GB".parse().unwrap();
+        assert_eq!(normalize("march twenty third", &gb), "23 March");
+    }
+
+    #[test]
+    fn unknown_language_falls_back_to_english() {
+        let locale = Locale::for_language("ja");
+        assert_eq!(normalize("two point five", &locale), "2.5");
+        assert!("xx".parse::<Locale>().is_err());
+    }
+}
@@ -1,20 +1,73 @@
 use evdev::{Device, EventSummary, KeyCode};
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
 use std::time::{Duration, Instant};
 
-/// Scan `/dev/input/event*` for all keyboard devices that support KEY_RIGHTCTRL.
+/// The push-to-talk hotkey used when `--key` is not given.
+pub const DEFAULT_HOTKEY: &str = "rightctrl";
+
+/// A push-to-talk hotkey: a single key or a combination held together,
+/// written as evdev key names joined by `+` (e.g. `rightctrl`, `leftmeta+space`).
+#[derive(Clone)]
+pub struct Hotkey {
+    keys: Vec<KeyCode>,
+}
+
+impl FromStr for Hotkey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let keys = s
+            .split('+')
+            .map(|part| {
+                let name = part.trim().to_uppercase();
+                let name = if name.starts_with("KEY_") || name.starts_with("BTN_") {
+                    name
+                } else {
+                    format!("KEY_{name}")
+                };
+                KeyCode::from_str(&name).map_err(|_| format!("unknown key \"{}\"", part.trim()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        // A repeated key could never complete the combination.
+        if let Some(i) = (1..keys.len()).find(|&i| keys[..i].contains(&keys[i])) {
+            let name = s.split('+').nth(i).unwrap_or_default().trim();
+            return Err(format!("key \"{name}\" is listed more than once"));
+        }
+        Ok(Hotkey { keys })
+    }
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<String> = self
+            .keys
+            .iter()
+            .map(|key| {
+                let name = format!("{key:?}");
+                name.strip_prefix("KEY_").unwrap_or(&name).to_lowercase()
+            })
+            .collect();
+        write!(f, "{}", names.join("+"))
+    }
+}
+
+/// Scan `/dev/input/event*` for all keyboard devices that support every key in `hotkey`.
 /// Returns an empty vec with a warning on stderr if none are found
 /// (e.g. user is not in the `input` group).
-pub fn find_keyboard_devices() -> Vec<Device> {
+pub fn find_keyboard_devices(hotkey: &Hotkey) -> Vec<Device> {
     let devices: Vec<Device> = evdev::enumerate()
         .filter_map(|(_path, device)| {
-            let dominated = device.supported_keys()?.contains(KeyCode::KEY_RIGHTCTRL);
-            dominated.then_some(device)
+            let supported = device.supported_keys()?;
+            let supports_all = hotkey.keys.iter().all(|&key| supported.contains(key));
+            supports_all.then_some(device)
         })
         .collect();
 
     if devices.is_empty() {
         eprintln!(
-            "stt-typer: could not find a keyboard device with KEY_RIGHTCTRL. \
+            "stt-typer: could not find a keyboard device with {hotkey}. \
              Ensure you are in the 'input' group (sudo usermod -aG input $USER, then re-login)."
         );
     }
@@ -22,13 +75,23 @@ pub fn find_keyboard_devices() -> Vec<Device> {
     devices
 }
 
-/// Wait for a right CTRL key press on any of the given devices.
-/// Returns `true` if the key was pressed, `false` if the timeout expired.
-pub fn wait_for_right_ctrl(devices: &mut [Device], timeout: Duration) -> Result<bool, String> {
+/// Wait for `hotkey` to be pressed on any of the given devices. For a combination,
+/// this fires on the key press that completes it.
+/// Returns `true` if the hotkey was pressed, `false` if the timeout expired.
+pub fn wait_for_hotkey(
+    devices: &mut [Device],
+    hotkey: &Hotkey,
+    timeout: Duration,
+) -> Result<bool, String> {
+    // Seed with keys already held (e.g. a modifier pressed before we started waiting).
+    let mut held: HashSet<KeyCode> = HashSet::new();
     for device in devices.iter_mut() {
         device
             .set_nonblocking(true)
             .map_err(|e| format!("failed to set device non-blocking: {e}"))?;
+        if let Ok(state) = device.get_key_state() {
+            held.extend(hotkey.keys.iter().filter(|&&key| state.contains(key)));
+        }
     }
 
     let start = Instant::now();
@@ -42,10 +105,23 @@ pub fn wait_for_right_ctrl(devices: &mut [Device], timeout: Duration) -> Result<
             match device.fetch_events() {
                 Ok(events) => {
                     for event in events {
-                        if let EventSummary::Key(_, KeyCode::KEY_RIGHTCTRL, 1) =
-                            event.destructure()
-                        {
-                            return Ok(true);
+                        // value 1 = press, 0 = release, 2 = autorepeat
+                        if let EventSummary::Key(_, key, value) = event.destructure() {
+                            if !hotkey.keys.contains(&key) {
+                                continue;
+                            }
+                            match value {
+                                1 => {
+                                    held.insert(key);
+                                    if held.len() == hotkey.keys.len() {
+                                        return Ok(true);
+                                    }
+                                }
+                                0 => {
+                                    held.remove(&key);
+                                }
+                                _ => {}
+                            }
                         }
                     }
                 }
@@ -58,10 +134,12 @@ pub fn wait_for_right_ctrl(devices: &mut [Device], timeout: Duration) -> Result<
     }
 }
 
-/// Wait for the right CTRL key to be released on any of the given devices.
-/// Returns `true` if the key was released, `false` if the timeout expired.
-pub fn wait_for_right_ctrl_release(
+/// Wait for `hotkey` to be released on any of the given devices. For a combination,
+/// releasing any of its keys counts.
+/// Returns `true` if the hotkey was released, `false` if the timeout expired.
+pub fn wait_for_hotkey_release(
     devices: &mut [Device],
+    hotkey: &Hotkey,
     timeout: Duration,
 ) -> Result<bool, String> {
     for device in devices.iter_mut() {
@@ -82,8 +160,8 @@ pub fn wait_for_right_ctrl_release(
                 Ok(events) => {
                     for event in events {
                         // value 0 = key release
-                        if let EventSummary::Key(_, KeyCode::KEY_RIGHTCTRL, 0) =
-                            event.destructure()
+                        if let EventSummary::Key(_, key, 0) = event.destructure()
+                            && hotkey.keys.contains(&key)
                         {
                             return Ok(true);
                         }
@@ -97,3 +175,44 @@ pub fn wait_for_right_ctrl_release(
         std::thread::sleep(Duration::from_millis(10));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<Hotkey, String> {
+        s.parse()
+    }
+
+    #[test]
+    fn parses_single_keys_and_combinations() {
+        assert_eq!(parse("rightctrl").unwrap().keys, [KeyCode::KEY_RIGHTCTRL]);
+        assert_eq!(
+            parse("LeftMeta + space").unwrap().keys,
+            [KeyCode::KEY_LEFTMETA, KeyCode::KEY_SPACE]
+        );
+        assert_eq!(parse("KEY_F12").unwrap().keys, [KeyCode::KEY_F12]);
+        assert_eq!(parse("btn_side").unwrap().keys, [KeyCode::BTN_SIDE]);
+    }
+
+    #[test]
+    fn rejects_unknown_and_repeated_keys() {
+        assert_eq!(parse("rightctl").err().unwrap(), "unknown key \"rightctl\"");
+        assert_eq!(parse("rightctrl+").err().unwrap(), "unknown key \"\"");
+        assert_eq!(
+            parse("rightctrl+rightctrl").err().unwrap(),
+            "key \"rightctrl\" is listed more than once"
+        );
+        // Spellings of the same key count as repeats too.
+        assert!(parse("leftmeta+space+KEY_LEFTMETA").is_err());
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for s in ["rightctrl", "leftmeta+space", "f12"] {
+            assert_eq!(parse(s).unwrap().to_string(), s);
+        }
+        assert_eq!(parse("KEY_LEFTALT+Z").unwrap().to_string(), "leftalt+z");
+        assert_eq!(parse("btn_side").unwrap().to_string(), "btn_side");
+    }
+}
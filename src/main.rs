@@ -1,12 +1,28 @@
+mod analyze;
 mod audio;
+mod backend;
+mod cleanup;
+mod hallucination;
+mod itn;
 mod keyboard;
+mod pcm;
+mod pipeline;
+mod postprocess;
+mod replace;
+mod setup;
+#[cfg(test)]
+mod test_support;
 mod transcribe;
+mod vad;
+mod watch;
+mod words;
 
 use anyhow::{Context, Result, bail};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::SampleFormat;
-use std::path::PathBuf;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
@@ -15,21 +31,282 @@ use std::time::Duration;
 const DEFAULT_MODEL_PATH: &str = ".local/share/stt-mcp/ggml-base.bin";
 
 #[derive(Parser)]
-#[command(name = "stt-typer", about = "Hold right CTRL to speak, release to transcribe and type into the active window")]
+#[command(
+    name = "stt-typer",
+    about = "Hold the push-to-talk key (right CTRL by default) to speak, release to transcribe and type into the active window"
+)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// Push-to-talk key or combination, as evdev key names joined by "+" (e.g. "leftmeta+space")
+    #[arg(short, long, default_value = keyboard::DEFAULT_HOTKEY, global = true)]
+    key: keyboard::Hotkey,
+
     /// Maximum seconds to record (safety cap if key is held too long)
     #[arg(short, long, default_value_t = 30)]
     max_duration: u32,
 
-    /// Language hint for Whisper (default: "en")
-    #[arg(short, long, default_value = "en")]
+    /// Input device to capture from (repeat to mix several microphones; default: system default)
+    #[arg(short = 'd', long = "device", global = true)]
+    devices: Vec<String>,
+
+    /// List available input devices and exit
+    #[arg(long)]
+    list_devices: bool,
+
+    /// Preferred capture sample rate in Hz (falls back to the device default if unsupported)
+    #[arg(long, global = true)]
+    sample_rate: Option<u32>,
+
+    /// Preferred capture buffer size in frames (falls back to the device default if unsupported)
+    #[arg(long, global = true)]
+    buffer_size: Option<u32>,
+
+    /// Capture raw PCM from this named pipe (FIFO) instead of an input device
+    #[arg(long, value_name = "PATH", global = true, conflicts_with = "devices")]
+    input_pipe: Option<PathBuf>,
+
+    /// Sample rate of --input-pipe audio in Hz
+    #[arg(long, value_name = "HZ", default_value_t = 16000, value_parser = clap::value_parser!(u32).range(1..), global = true)]
+    pipe_rate: u32,
+
+    /// Channel count of --input-pipe audio
+    #[arg(long, default_value_t = 1, global = true)]
+    pipe_channels: u16,
+
+    /// Sample format of --input-pipe audio
+    #[arg(long, value_enum, default_value_t = pcm::PcmFormat::S16le, global = true)]
+    pipe_format: pcm::PcmFormat,
+
+    /// Resampler for converting audio to 16kHz: fast (linear), balanced (polyphase
+    /// windowed sinc) or high (long windowed sinc)
+    #[arg(long, value_enum, default_value = "fast", global = true)]
+    resample_quality: audio::ResampleQuality,
+
+    /// High-pass cutoff in Hz applied after removing DC offset, to cut mic rumble
+    /// before transcription (0 disables both)
+    #[arg(long, value_name = "HZ", default_value_t = 80.0, value_parser = parse_cutoff, global = true)]
+    highpass: f32,
+
+    /// Language hint for Whisper, or "auto" to detect it (default: "en")
+    #[arg(short, long, default_value = "en", global = true)]
     language: String,
 
+    /// Comma-separated languages Whisper may transcribe as (e.g. "en,de").
+    /// With --language auto, detection is coerced to the closest allowed language.
+    #[arg(short = 'A', long, value_delimiter = ',', global = true)]
+    allowed_languages: Vec<String>,
+
+    /// Re-decode with beam search when mean token confidence is below this (0–1)
+    #[arg(long, value_parser = parse_probability, global = true)]
+    retry_below: Option<f32>,
+
+    /// File of phrases (one per line) to strip from non-speech segments, replacing
+    /// the built-in per-language list; an empty file disables stripping
+    #[arg(long, global = true)]
+    hallucinations: Option<PathBuf>,
+
+    /// No-speech probability above which segments are checked for blocklisted phrases (0–1)
+    #[arg(long, default_value_t = 0.6, value_parser = parse_probability, global = true)]
+    no_speech_thold: f32,
+
+    /// Re-decode segments whose token entropy is below this (higher cuts more repetition)
+    #[arg(long, default_value_t = 2.4, global = true)]
+    entropy_thold: f32,
+
+    /// Re-decode segments whose average token log probability is below this
+    #[arg(long, default_value_t = -1.0, allow_negative_numbers = true, global = true)]
+    logprob_thold: f32,
+
+    /// Allow segments to start with a blank token (suppressed by default)
+    #[arg(long, global = true)]
+    no_suppress_blank: bool,
+
+    /// Suppress non-speech tokens so music and coughs don't appear as "[Music]" or "(coughs)"
+    #[arg(long, global = true)]
+    suppress_non_speech: bool,
+
+    /// Print each transcript's tokens to stdout as TSV (start_ms, end_ms, probability, text)
+    #[arg(long, global = true)]
+    print_tokens: bool,
+
+    /// Align --print-tokens timestamps with DTW using this model's alignment heads
+    /// (must match --model, e.g. "base.en" for ggml-base.en.bin)
+    #[arg(long, value_enum, requires = "print_tokens", global = true)]
+    dtw: Option<transcribe::DtwPreset>,
+
+    /// Sentence-case the transcript, drop filler words and collapse repeated words
+    #[arg(long, global = true)]
+    cleanup: bool,
+
+    /// Comma-separated filler words removed by --cleanup
+    #[arg(long, value_delimiter = ',', default_value = cleanup::DEFAULT_FILLERS, global = true)]
+    filler_words: Vec<String>,
+
+    /// Rewrite spoken numbers and dates as digits ("twenty third of march" → "23 March")
+    #[arg(long, global = true)]
+    normalize_numbers: bool,
+
+    /// Number and date style for --normalize-numbers: en, en-gb, de, fr, es, it, nl or pt
+    /// (default: the transcript's language, else en)
+    #[arg(long, global = true)]
+    number_locale: Option<itn::Locale>,
+
+    /// File of "spoken phrase = replacement" lines applied to every transcript
+    /// (e.g. "cube control = kubectl")
+    #[arg(long, global = true)]
+    replacements: Option<PathBuf>,
+
+    /// Comma-separated post-processing steps to run, in order: cleanup, numbers,
+    /// replace (instead of --cleanup and --normalize-numbers; replace needs --replacements)
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        conflicts_with_all = ["cleanup", "normalize_numbers"],
+        global = true
+    )]
+    postprocess: Option<Vec<postprocess::Step>>,
+
+    /// Path to a ggml Silero VAD model; cuts non-speech before transcribing
+    #[arg(long, env = "WHISPER_VAD_MODEL_PATH", global = true)]
+    vad_model: Option<PathBuf>,
+
+    /// Speech probability threshold for --vad-model (0–1)
+    #[arg(long, default_value_t = 0.5, value_parser = parse_probability, global = true)]
+    vad_threshold: f32,
+
+    /// Where to transcribe: the local Whisper model or --remote-url
+    #[arg(long, value_enum, default_value_t = BackendKind::Local, global = true)]
+    backend: BackendKind,
+
+    /// OpenAI-compatible transcription endpoint (e.g. https://api.openai.com/v1/audio/transcriptions).
+    /// The API key is read from STT_REMOTE_API_KEY
+    #[arg(long, env = "STT_REMOTE_URL", global = true)]
+    remote_url: Option<String>,
+
+    /// Model name sent to --remote-url
+    #[arg(long, default_value = "whisper-1", global = true)]
+    remote_model: String,
+
+    /// Re-send local transcripts with confidence below this (0–1) to --remote-url
+    #[arg(long, value_parser = parse_probability, global = true)]
+    remote_fallback_below: Option<f32>,
+
     /// Path to Whisper model file (default: ~/.local/share/stt-mcp/ggml-base.bin or WHISPER_MODEL_PATH)
-    #[arg(short = 'M', long, env = "WHISPER_MODEL_PATH")]
+    #[arg(short = 'M', long, env = "WHISPER_MODEL_PATH", global = true)]
     model: Option<PathBuf>,
 }
 
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+enum BackendKind {
+    Local,
+    Remote,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Transcribe a WAV or raw PCM file (or stdin) and print the transcript
+    Transcribe(TranscribeInput),
+    /// Report audio quality (level, clipping, DC offset, noise) without transcribing
+    Analyze(AnalyzeInput),
+    /// Check permissions, pick a microphone, download a model sized for this
+    /// machine and run a test transcription
+    Setup {
+        /// Model to download instead of asking (default: recommended for this machine)
+        #[arg(long, value_enum)]
+        model_size: Option<setup::ModelSize>,
+
+        /// Accept the default answer to every question
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Seconds to record for the test transcription (0 to skip it)
+        #[arg(long, default_value_t = 5)]
+        secs: u64,
+    },
+    /// Transcribe WAV files dropped into a directory, writing a .txt next to each
+    Watch {
+        /// Directory to watch
+        dir: PathBuf,
+
+        /// Seconds between directory scans
+        #[arg(long, default_value_t = 2)]
+        interval: u64,
+    },
+    /// Record from the microphone for a fixed time and print the transcript
+    Record {
+        /// Seconds to record
+        #[arg(long, default_value_t = 5)]
+        secs: u64,
+    },
+}
+
+#[derive(clap::Args)]
+struct TranscribeInput {
+    /// Audio file to transcribe (WAV, or raw PCM described by --rate/--channels/--format)
+    #[arg(required_unless_present = "stdin")]
+    file: Option<PathBuf>,
+
+    /// Read audio from stdin instead of a file
+    #[arg(long, conflicts_with = "file")]
+    stdin: bool,
+
+    #[command(flatten)]
+    raw: RawPcmArgs,
+}
+
+#[derive(clap::Args)]
+struct AnalyzeInput {
+    /// Audio file to analyze (default: record from the microphone)
+    file: Option<PathBuf>,
+
+    /// Read audio from stdin instead of a file
+    #[arg(long, conflicts_with = "file")]
+    stdin: bool,
+
+    /// Seconds to record when analyzing the microphone
+    #[arg(long, default_value_t = 5, conflicts_with_all = ["file", "stdin"])]
+    secs: u64,
+
+    #[command(flatten)]
+    raw: RawPcmArgs,
+}
+
+/// How to interpret headerless audio input.
+#[derive(clap::Args)]
+struct RawPcmArgs {
+    /// Sample rate of raw PCM input in Hz (WAV input uses its header)
+    #[arg(long, default_value_t = 16000, value_parser = clap::value_parser!(u32).range(1..))]
+    rate: u32,
+
+    /// Channel count of raw PCM input
+    #[arg(long, default_value_t = 1)]
+    channels: u16,
+
+    /// Sample format of raw PCM input
+    #[arg(long, value_enum, default_value_t = pcm::PcmFormat::S16le)]
+    format: pcm::PcmFormat,
+}
+
+fn parse_probability(s: &str) -> Result<f32, String> {
+    let value: f32 = s.parse().map_err(|e| format!("{e}"))?;
+    if !(0.0..=1.0).contains(&value) {
+        return Err(format!("{value} is not between 0 and 1"));
+    }
+    Ok(value)
+}
+
+/// A high-pass cutoff: 0 (off) up to just below the 8 kHz Nyquist frequency.
+fn parse_cutoff(s: &str) -> Result<f32, String> {
+    let value: f32 = s.parse().map_err(|e| format!("{e}"))?;
+    if !(0.0..8000.0).contains(&value) {
+        return Err(format!("{value} Hz is not between 0 and 8000"));
+    }
+    Ok(value)
+}
+
 fn dirs_path() -> PathBuf {
     std::env::var("HOME")
         .map(PathBuf::from)
@@ -158,19 +435,419 @@ fn detect_ydotool_socket() {
 }
 
 fn main() -> Result<()> {
-    let args = Args::parse();
+    let mut args = Args::parse();
+
+    if args.list_devices {
+        for name in audio::list_input_devices()? {
+            println!("{name}");
+        }
+        return Ok(());
+    }
+
+    validate_languages(&args)?;
+
+    match args.command.take() {
+        Some(Commands::Transcribe(input)) => {
+            let mut pipeline = load_pipeline(&args)?;
+            transcribe_input(&mut pipeline, &input, args.resample_quality)
+        }
+        Some(Commands::Analyze(input)) => analyze_input(&args, &input),
+        Some(Commands::Watch { dir, interval }) => {
+            let mut pipeline = load_pipeline(&args)?;
+            watch::watch(
+                &mut pipeline,
+                &dir,
+                Duration::from_secs(interval),
+                args.resample_quality,
+            )
+        }
+        Some(Commands::Record { secs }) => {
+            let capture = capture_options(&args)?;
+            let mut pipeline = load_pipeline(&args)?;
+            record_once(&mut pipeline, &capture, secs)
+        }
+        Some(Commands::Setup {
+            model_size,
+            yes,
+            secs,
+        }) => run_setup(args, model_size, yes, secs),
+        None => push_to_talk(args),
+    }
+}
+
+/// Reject unknown language codes and a --language outside --allowed-languages.
+fn validate_languages(args: &Args) -> Result<()> {
+    transcribe::validate_language(&args.language)?;
+    for lang in &args.allowed_languages {
+        if lang == "auto" {
+            bail!("\"auto\" cannot be used in --allowed-languages");
+        }
+        transcribe::validate_language(lang)?;
+    }
+    if args.language != "auto"
+        && !args.allowed_languages.is_empty()
+        && !args.allowed_languages.contains(&args.language)
+    {
+        bail!(
+            "language \"{}\" is not in --allowed-languages ({})",
+            args.language,
+            args.allowed_languages.join(",")
+        );
+    }
+    Ok(())
+}
+
+/// Set up the transcription backend(s) and optional VAD model, and collect
+/// transcription settings.
+fn load_pipeline(args: &Args) -> Result<pipeline::Pipeline> {
+    let remote = match &args.remote_url {
+        Some(url) => Some(backend::RemoteApi {
+            url: url.clone(),
+            model: args.remote_model.clone(),
+            api_key: std::env::var("STT_REMOTE_API_KEY").ok(),
+        }),
+        None if args.backend == BackendKind::Remote || args.remote_fallback_below.is_some() => {
+            bail!("--backend remote and --remote-fallback-below need --remote-url")
+        }
+        None => None,
+    };
+    if remote.is_some()
+        && args.backend == BackendKind::Local
+        && args.remote_fallback_below.is_none()
+    {
+        eprintln!(
+            "[stt-typer] warning: --remote-url is set but unused; add --backend remote \
+             or --remote-fallback-below to use it"
+        );
+    }
+    // The API detects the language on its own and can't be restricted to a set;
+    // as a fallback it is handed the language the local pass settled on instead.
+    if args.backend == BackendKind::Remote
+        && args.language == "auto"
+        && !args.allowed_languages.is_empty()
+    {
+        bail!(
+            "--backend remote can't restrict --language auto to --allowed-languages; \
+             pass a single --language instead"
+        );
+    }
+    // The text response has no tokens or segments, so the decoding options and
+    // per-segment clean-up of the local model have nothing to work on.
+    if args.backend == BackendKind::Remote {
+        let local_only: Vec<&str> = [
+            ("--retry-below", args.retry_below.is_some()),
+            ("--print-tokens", args.print_tokens),
+            ("--hallucinations", args.hallucinations.is_some()),
+            ("--no-suppress-blank", args.no_suppress_blank),
+            ("--suppress-non-speech", args.suppress_non_speech),
+        ]
+        .into_iter()
+        .filter_map(|(flag, given)| given.then_some(flag))
+        .collect();
+        if !local_only.is_empty() {
+            bail!(
+                "{} only apply to local transcription and can't be used with --backend remote",
+                local_only.join(", ")
+            );
+        }
+    }
+
+    let (backend, fallback): (Box<dyn backend::TranscriptionBackend>, _) =
+        match (args.backend, remote) {
+            (BackendKind::Remote, Some(remote)) => {
+                eprintln!("[stt-typer] transcribing with {}", remote.url);
+                (Box::new(remote), None)
+            }
+            (_, remote) => {
+                let model_path = args
+                    .model
+                    .clone()
+                    .unwrap_or_else(|| dirs_path().join(DEFAULT_MODEL_PATH));
+                eprintln!(
+                    "[stt-typer] loading whisper model from {}",
+                    model_path.display()
+                );
+                let ctx = transcribe::create_context(&model_path, args.dtw)
+                    .context("failed to load whisper model")?;
+                eprintln!("[stt-typer] model loaded");
+
+                let fallback = remote
+                    .zip(args.remote_fallback_below)
+                    .map(|(remote, below)| pipeline::Fallback {
+                        backend: Box::new(remote),
+                        below,
+                    });
+                (Box::new(backend::LocalWhisper::new(ctx)), fallback)
+            }
+        };
+
+    let speech_detector = match &args.vad_model {
+        Some(path) => {
+            eprintln!("[stt-typer] loading VAD model from {}", path.display());
+            Some(vad::SpeechDetector::new(path, args.vad_threshold)?)
+        }
+        None => None,
+    };
 
-    let model_path = args
-        .model
-        .unwrap_or_else(|| dirs_path().join(DEFAULT_MODEL_PATH));
+    let hallucinations = match &args.hallucinations {
+        Some(path) => {
+            let contents = std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            Some(hallucination::parse_list(&contents))
+        }
+        None => None,
+    };
+
+    let replacements = match &args.replacements {
+        Some(path) => {
+            let contents = std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            let replacements = replace::Replacements::parse(&contents)
+                .with_context(|| format!("invalid replacements file {}", path.display()))?;
+            Some(replacements)
+        }
+        None => None,
+    };
+
+    // Without --postprocess, the individual flags enable steps in the default order.
+    let steps = match &args.postprocess {
+        Some(steps) => steps.clone(),
+        None => [
+            (postprocess::Step::Cleanup, args.cleanup),
+            (postprocess::Step::Numbers, args.normalize_numbers),
+            (postprocess::Step::Replace, replacements.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(step, enabled)| enabled.then_some(step))
+        .collect(),
+    };
+    let post = postprocess::PostProcess::build(
+        &steps,
+        &args.filler_words,
+        args.number_locale,
+        replacements,
+    )?;
+
+    Ok(pipeline::Pipeline {
+        backend,
+        fallback,
+        highpass: (args.highpass > 0.0).then_some(args.highpass),
+        speech_detector,
+        opts: transcribe::TranscribeOptions {
+            language: args.language.clone(),
+            allowed_languages: args.allowed_languages.clone(),
+            retry_below: args.retry_below,
+            tokens: args.print_tokens,
+            hallucinations,
+            no_speech_thold: args.no_speech_thold,
+            entropy_thold: args.entropy_thold,
+            logprob_thold: args.logprob_thold,
+            suppress_blank: !args.no_suppress_blank,
+            suppress_non_speech: args.suppress_non_speech,
+        },
+        post,
+        print_tokens: args.print_tokens,
+    })
+}
+
+/// Read and decode audio from `file`, or stdin if `file` is `None`, into 16kHz mono.
+fn read_audio(
+    file: Option<&PathBuf>,
+    raw: &RawPcmArgs,
+    quality: audio::ResampleQuality,
+) -> Result<Vec<f32>> {
+    let mut bytes = Vec::new();
+    match file {
+        Some(path) => {
+            bytes = std::fs::read(path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+        }
+        None => {
+            std::io::stdin()
+                .lock()
+                .read_to_end(&mut bytes)
+                .context("failed to read audio from stdin")?;
+        }
+    }
+
+    let raw = pcm::RawPcm {
+        rate: raw.rate,
+        channels: raw.channels,
+        format: raw.format,
+    };
+    let decoded = pcm::decode(&bytes, &raw)?;
+    let samples = audio::to_whisper_format(
+        decoded.samples,
+        decoded.rate,
+        decoded.channels as usize,
+        quality,
+    );
+    if samples.is_empty() {
+        bail!("no audio samples in input");
+    }
+    Ok(samples)
+}
+
+/// `stt-typer transcribe`: decode a file or stdin, transcribe it and print the text.
+fn transcribe_input(
+    pipeline: &mut pipeline::Pipeline,
+    input: &TranscribeInput,
+    quality: audio::ResampleQuality,
+) -> Result<()> {
+    let file = input.file.as_ref().filter(|_| !input.stdin);
+    let samples = read_audio(file, &input.raw, quality)?;
+    eprintln!(
+        "[stt-typer] read {:.1}s of audio, transcribing...",
+        samples.len() as f32 / 16000.0
+    );
+
+    if let Some(text) = pipeline.process(samples)? {
+        println!("{text}");
+    }
+    Ok(())
+}
+
+/// `stt-typer analyze`: print an audio quality report for a file, stdin or a
+/// short microphone capture.
+fn analyze_input(args: &Args, input: &AnalyzeInput) -> Result<()> {
+    let samples = if input.stdin || input.file.is_some() {
+        read_audio(input.file.as_ref(), &input.raw, args.resample_quality)?
+    } else {
+        let capture = capture_options(args)?;
+        eprintln!(
+            "[stt-typer] recording for {}s, speak normally...",
+            input.secs
+        );
+        play_beep();
+        let stop = Arc::new(AtomicBool::new(false));
+        let samples = audio::record_until_stopped(stop, Duration::from_secs(input.secs), &capture)
+            .context("recording failed")?;
+        if samples.is_empty() {
+            bail!("no audio captured");
+        }
+        samples
+    };
+
+    print!("{}", analyze::analyze(&samples));
+    Ok(())
+}
+
+/// `stt-typer setup`: run the setup steps, test the result and print the command to use.
+fn run_setup(
+    mut args: Args,
+    model_size: Option<setup::ModelSize>,
+    assume_yes: bool,
+    secs: u64,
+) -> Result<()> {
+    let default_model = dirs_path().join(DEFAULT_MODEL_PATH);
+    let model_dir = default_model.parent().unwrap_or(Path::new("."));
+    let choice = setup::run(
+        model_dir,
+        args.model.as_deref(),
+        &args.key,
+        &args.devices,
+        model_size,
+        assume_yes,
+    )?;
+
+    args.model = Some(choice.model.clone());
+    args.devices = choice.devices.clone();
+    if secs > 0 {
+        eprintln!("[stt-typer] test transcription: say a sentence after the beep");
+        let capture = capture_options(&args)?;
+        let mut pipeline = load_pipeline(&args)?;
+        record_once(&mut pipeline, &capture, secs)?;
+    }
+
+    let mut command = String::from("stt-typer");
+    if choice.model != default_model {
+        command.push_str(&format!(
+            " --model {}",
+            shell_quote(&choice.model.to_string_lossy())
+        ));
+    }
+    for device in &choice.devices {
+        command.push_str(&format!(" --device {}", shell_quote(device)));
+    }
+    if args.key.to_string() != keyboard::DEFAULT_HOTKEY {
+        command.push_str(&format!(" --key {}", args.key));
+    }
+    eprintln!(
+        "[stt-typer] setup complete, start push-to-talk with the command below \
+         (it sets only the model, microphone and key; add any other options you use):"
+    );
+    println!("{command}");
+    Ok(())
+}
+
+/// Quote `s` for a POSIX shell, leaving plain words as they are.
+fn shell_quote(s: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./:=+,@%".contains(c);
+    if !s.is_empty() && s.chars().all(plain) {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', "'\\''"))
+    }
+}
+
+/// Capture settings from the command line. Fails early if a configured input
+/// device doesn't exist, or starts reading the input pipe if one is configured.
+fn capture_options(args: &Args) -> Result<audio::CaptureOptions> {
+    let pipe = match &args.input_pipe {
+        Some(path) => {
+            let raw = pcm::RawPcm {
+                rate: args.pipe_rate,
+                channels: args.pipe_channels,
+                format: args.pipe_format,
+            };
+            Some(Arc::new(audio::PipeInput::open(path, raw)?))
+        }
+        None => {
+            audio::check_input_devices(&args.devices)?;
+            None
+        }
+    };
+    Ok(audio::CaptureOptions {
+        sample_rate: args.sample_rate,
+        buffer_size: args.buffer_size,
+        devices: args.devices.clone(),
+        resample_quality: args.resample_quality,
+        pipe,
+    })
+}
+
+/// `stt-typer record`: record for `secs` seconds, transcribe and print the text.
+fn record_once(
+    pipeline: &mut pipeline::Pipeline,
+    capture: &audio::CaptureOptions,
+    secs: u64,
+) -> Result<()> {
+    eprintln!("[stt-typer] recording for {secs}s...");
+    play_beep();
+    let stop = Arc::new(AtomicBool::new(false));
+    let samples = audio::record_until_stopped(stop, Duration::from_secs(secs), capture)
+        .context("recording failed")?;
+    if samples.is_empty() {
+        bail!("no audio captured");
+    }
+    eprintln!(
+        "[stt-typer] recorded {:.1}s, transcribing...",
+        samples.len() as f32 / 16000.0
+    );
+
+    if let Some(text) = pipeline.process(samples)? {
+        println!("{text}");
+    }
+    Ok(())
+}
+
+/// Default mode: wait for the hotkey, record until release, transcribe and type.
+fn push_to_talk(args: Args) -> Result<()> {
+    let capture = capture_options(&args)?;
 
-    // Preflight checks
     detect_ydotool_socket();
 
-    eprintln!("[stt-typer] loading whisper model from {}", model_path.display());
-    let ctx = transcribe::create_context(&model_path)
-        .context("failed to load whisper model")?;
-    eprintln!("[stt-typer] model loaded");
+    let mut pipeline = load_pipeline(&args)?;
 
     // Check ydotool is available
     let ydotool_check = Command::new("ydotool")
@@ -182,7 +859,7 @@ fn main() -> Result<()> {
         Err(e) => bail!("ydotool not found: {e}\nInstall with: sudo dnf install ydotool && sudo systemctl enable --now ydotool"),
     }
 
-    let devices = keyboard::find_keyboard_devices();
+    let devices = keyboard::find_keyboard_devices(&args.key);
     if devices.is_empty() {
         bail!("no keyboard device found — ensure you are in the 'input' group");
     }
@@ -190,27 +867,28 @@ fn main() -> Result<()> {
 
     // We need two independent device handles: one for the wait-for-press thread,
     // one for the wait-for-release thread. Re-enumerate to get separate handles.
-    let mut press_devices = keyboard::find_keyboard_devices();
-    let mut release_devices = keyboard::find_keyboard_devices();
+    let mut press_devices = keyboard::find_keyboard_devices(&args.key);
+    let mut release_devices = keyboard::find_keyboard_devices(&args.key);
     drop(devices);
 
     let max_duration = Duration::from_secs(args.max_duration as u64);
-    let lang = args.language;
 
-    eprintln!("[stt-typer] ready — hold right CTRL to speak, release to stop ({lang}, max {}s)",
-             args.max_duration);
+    eprintln!(
+        "[stt-typer] ready — hold {} to speak, release to stop ({}, max {}s)",
+        args.key, args.language, args.max_duration
+    );
 
     loop {
-        // Wait for right CTRL press (no timeout — wait forever)
-        match keyboard::wait_for_right_ctrl(&mut press_devices, Duration::from_secs(86400)) {
+        // Wait for hotkey press (no timeout — wait forever)
+        match keyboard::wait_for_hotkey(&mut press_devices, &args.key, Duration::from_secs(86400)) {
             Ok(true) => {}
             Ok(false) => continue,
             Err(e) => {
                 eprintln!("[stt-typer] keyboard error: {e}");
                 eprintln!("[stt-typer] re-enumerating keyboard devices...");
                 std::thread::sleep(Duration::from_secs(2));
-                press_devices = keyboard::find_keyboard_devices();
-                release_devices = keyboard::find_keyboard_devices();
+                press_devices = keyboard::find_keyboard_devices(&args.key);
+                release_devices = keyboard::find_keyboard_devices(&args.key);
                 if press_devices.is_empty() {
                     eprintln!("[stt-typer] no keyboard devices found, retrying in 5s...");
                     std::thread::sleep(Duration::from_secs(5));
@@ -219,7 +897,7 @@ fn main() -> Result<()> {
             }
         }
 
-        eprintln!("[stt-typer] recording... (release right CTRL to stop)");
+        eprintln!("[stt-typer] recording... (release {} to stop)", args.key);
         play_beep();
 
         // Start recording, stop when key is released or max_duration reached
@@ -228,14 +906,18 @@ fn main() -> Result<()> {
 
         // Spawn thread to wait for key release
         let mut rel_devs = std::mem::take(&mut release_devices);
+        let hotkey = args.key.clone();
         let key_thread = std::thread::spawn(move || {
-            let result =
-                keyboard::wait_for_right_ctrl_release(&mut rel_devs, Duration::from_secs(86400));
+            let result = keyboard::wait_for_hotkey_release(
+                &mut rel_devs,
+                &hotkey,
+                Duration::from_secs(86400),
+            );
             stop_for_key.store(true, Ordering::Relaxed);
             (rel_devs, result)
         });
 
-        let samples = match audio::record_until_stopped(stop, max_duration) {
+        let samples = match audio::record_until_stopped(stop, max_duration, &capture) {
             Ok(s) => s,
             Err(e) => {
                 eprintln!("[stt-typer] recording failed: {e}");
@@ -243,8 +925,8 @@ fn main() -> Result<()> {
                 release_devices = devs;
                 if let Err(ref ke) = key_result {
                     eprintln!("[stt-typer] key release error: {ke}");
-                    press_devices = keyboard::find_keyboard_devices();
-                    release_devices = keyboard::find_keyboard_devices();
+                    press_devices = keyboard::find_keyboard_devices(&args.key);
+                    release_devices = keyboard::find_keyboard_devices(&args.key);
                 }
                 continue;
             }
@@ -257,8 +939,8 @@ fn main() -> Result<()> {
         if let Err(ref e) = key_result {
             eprintln!("[stt-typer] key release error: {e}");
             eprintln!("[stt-typer] re-enumerating keyboard devices...");
-            press_devices = keyboard::find_keyboard_devices();
-            release_devices = keyboard::find_keyboard_devices();
+            press_devices = keyboard::find_keyboard_devices(&args.key);
+            release_devices = keyboard::find_keyboard_devices(&args.key);
         }
 
         if samples.is_empty() {
@@ -269,22 +951,60 @@ fn main() -> Result<()> {
         let duration_secs = samples.len() as f32 / 16000.0;
         eprintln!("[stt-typer] recorded {duration_secs:.1}s, transcribing...");
 
-        let text = match transcribe::transcribe_with_context(&ctx, &samples, &lang) {
-            Ok(t) => t,
+        let text = match pipeline.process(samples) {
+            Ok(Some(text)) => text,
+            Ok(None) => continue,
             Err(e) => {
                 eprintln!("[stt-typer] transcription failed: {e}");
                 continue;
             }
         };
 
-        if text.is_empty() {
-            eprintln!("[stt-typer] (empty transcription)");
-            continue;
-        }
-
         eprintln!("[stt-typer] typing: {text}");
         if let Err(e) = type_text(&text) {
             eprintln!("[stt-typer] typing failed: {e}");
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_probabilities_between_0_and_1() {
+        assert_eq!(parse_probability("0"), Ok(0.0));
+        assert_eq!(parse_probability("0.6"), Ok(0.6));
+        assert_eq!(parse_probability("1"), Ok(1.0));
+        assert_eq!(
+            parse_probability("1.5"),
+            Err("1.5 is not between 0 and 1".to_string())
+        );
+        assert_eq!(
+            parse_probability("-0.1"),
+            Err("-0.1 is not between 0 and 1".to_string())
+        );
+        assert!(parse_probability("high").is_err());
+    }
+
+    #[test]
+    fn shell_quote_wraps_anything_but_plain_words() {
+        assert_eq!(
+            shell_quote("/home/me/ggml-small.bin"),
+            "/home/me/ggml-small.bin"
+        );
+        assert_eq!(shell_quote("USB Mic (2)"), "'USB Mic (2)'");
+        assert_eq!(
+            shell_quote("Bob's \"mic\" $HOME"),
+            "'Bob'\\''s \"mic\" $HOME'"
+        );
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn key_is_accepted_after_a_subcommand() {
+        let args = Args::try_parse_from(["stt-typer", "setup", "--key", "leftmeta+space"]).unwrap();
+        assert!(matches!(args.command, Some(Commands::Setup { .. })));
+        assert_eq!(args.key.to_string(), "leftmeta+space");
+    }
+}
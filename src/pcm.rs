@@ -0,0 +1,315 @@
+use anyhow::{Result, bail};
+
+/// Sample encoding of headerless PCM input.
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum PcmFormat {
+    /// Signed 16-bit little-endian
+    S16le,
+    /// 32-bit float little-endian
+    F32le,
+}
+
+impl PcmFormat {
+    fn bytes_per_sample(self) -> usize {
+        match self {
+            PcmFormat::S16le => 2,
+            PcmFormat::F32le => 4,
+        }
+    }
+}
+
+/// How to interpret input that has no WAV header.
+#[derive(Clone, Copy)]
+pub struct RawPcm {
+    pub rate: u32,
+    pub channels: u16,
+    pub format: PcmFormat,
+}
+
+impl RawPcm {
+    /// Bytes per frame: one sample for every channel.
+    pub fn frame_size(&self) -> usize {
+        self.channels as usize * self.format.bytes_per_sample()
+    }
+}
+
+/// Decoded interleaved samples with their rate and channel count.
+pub struct Decoded {
+    pub samples: Vec<f32>,
+    pub rate: u32,
+    pub channels: u16,
+}
+
+/// Decode `bytes` as WAV if it starts with a RIFF/WAVE header, otherwise as raw
+/// PCM described by `raw`.
+pub fn decode(bytes: &[u8], raw: &RawPcm) -> Result<Decoded> {
+    if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WAVE" {
+        return decode_wav(bytes);
+    }
+    decode_raw(bytes, raw)
+}
+
+/// Decode headerless PCM described by `raw`. A trailing partial frame, as left
+/// by a stream cut off mid-write, is dropped so channels stay aligned.
+pub fn decode_raw(bytes: &[u8], raw: &RawPcm) -> Result<Decoded> {
+    if raw.channels == 0 {
+        bail!("raw PCM input needs at least one channel");
+    }
+    if raw.rate == 0 {
+        bail!("raw PCM input needs a sample rate above 0 Hz");
+    }
+    let frame = raw.frame_size();
+    Ok(Decoded {
+        samples: decode_samples(&bytes[..bytes.len() / frame * frame], raw.format),
+        rate: raw.rate,
+        channels: raw.channels,
+    })
+}
+
+fn decode_samples(bytes: &[u8], format: PcmFormat) -> Vec<f32> {
+    match format {
+        PcmFormat::S16le => bytes
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / i16::MAX as f32)
+            .collect(),
+        PcmFormat::F32le => bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+    }
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+/// Parse a RIFF/WAVE file holding 8/16/24/32-bit integer or 32-bit float PCM.
+pub fn decode_wav(bytes: &[u8]) -> Result<Decoded> {
+    // (format tag, channels, rate, bits per sample)
+    let mut fmt: Option<(u16, u16, u32, u16)> = None;
+    let mut pos = 12;
+
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let size = u32_at(bytes, pos + 4) as usize;
+        let body = pos + 8;
+
+        match id {
+            b"fmt " => {
+                if size < 16 || body + 16 > bytes.len() {
+                    bail!("truncated WAV fmt chunk");
+                }
+                let mut tag = u16_at(bytes, body);
+                // WAVE_FORMAT_EXTENSIBLE keeps the real format in the sub-format GUID.
+                if tag == 0xFFFE && size >= 40 && body + 26 <= bytes.len() {
+                    tag = u16_at(bytes, body + 24);
+                }
+                fmt = Some((
+                    tag,
+                    u16_at(bytes, body + 2),
+                    u32_at(bytes, body + 4),
+                    u16_at(bytes, body + 14),
+                ));
+            }
+            b"data" => {
+                let Some((tag, channels, rate, bits)) = fmt else {
+                    bail!("WAV data chunk before fmt chunk");
+                };
+                if channels == 0 {
+                    bail!("WAV file has no channels");
+                }
+                if rate == 0 {
+                    bail!("WAV file has a sample rate of 0 Hz");
+                }
+                // Streamed WAVs (e.g. from ffmpeg on a pipe) leave the size unset.
+                let end = body.saturating_add(size).min(bytes.len());
+                let data = &bytes[body..end];
+                let samples = match (tag, bits) {
+                    (1, 8) => data.iter().map(|&b| (b as f32 - 128.0) / 128.0).collect(),
+                    (1, 16) => decode_samples(data, PcmFormat::S16le),
+                    (1, 24) => data
+                        .chunks_exact(3)
+                        .map(|b| {
+                            let v = i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8;
+                            v as f32 / 8_388_607.0
+                        })
+                        .collect(),
+                    (1, 32) => data
+                        .chunks_exact(4)
+                        .map(|b| {
+                            i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / i32::MAX as f32
+                        })
+                        .collect(),
+                    (3, 32) => decode_samples(data, PcmFormat::F32le),
+                    _ => bail!("unsupported WAV encoding (format {tag}, {bits}-bit)"),
+                };
+                return Ok(Decoded {
+                    samples,
+                    rate,
+                    channels,
+                });
+            }
+            _ => {}
+        }
+
+        // Chunks are padded to an even length.
+        pos = body.saturating_add(size).saturating_add(size & 1);
+    }
+
+    bail!("WAV file has no data chunk")
+}
+
+/// Encode 16kHz-style mono samples as a 16-bit PCM WAV file.
+pub fn encode_wav(samples: &[f32], rate: u32) -> Vec<u8> {
+    let data_len = samples.len() as u32 * 2;
+    let mut out = Vec::with_capacity(44 + data_len as usize);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + data_len).to_le_bytes());
+    out.extend_from_slice(b"WAVEfmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    out.extend_from_slice(&1u16.to_le_bytes()); // mono
+    out.extend_from_slice(&rate.to_le_bytes());
+    out.extend_from_slice(&(rate * 2).to_le_bytes());
+    out.extend_from_slice(&2u16.to_le_bytes());
+    out.extend_from_slice(&16u16.to_le_bytes());
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&data_len.to_le_bytes());
+    for &s in samples {
+        let v = (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        out.extend_from_slice(&v.to_le_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{WavEncoding, interleave, sine, wav};
+
+    const RAW: RawPcm = RawPcm {
+        rate: 16000,
+        channels: 1,
+        format: PcmFormat::S16le,
+    };
+
+    fn assert_close(a: &[f32], b: &[f32], tolerance: f32) {
+        assert_eq!(a.len(), b.len());
+        for (x, y) in a.iter().zip(b) {
+            assert!((x - y).abs() <= tolerance, "{x} != {y}");
+        }
+    }
+
+    #[test]
+    fn decodes_16_bit_wav() {
+        let tone = sine(440.0, 0.1, 22050);
+        let decoded = decode(&wav(&tone, 22050, 1, WavEncoding::Pcm16), &RAW).unwrap();
+        assert_eq!((decoded.rate, decoded.channels), (22050, 1));
+        assert_close(&decoded.samples, &tone, 1e-4);
+    }
+
+    #[test]
+    fn decodes_24_bit_and_float_wav() {
+        let tone = sine(1000.0, 0.05, 48000);
+        let decoded = decode(&wav(&tone, 48000, 1, WavEncoding::Pcm24), &RAW).unwrap();
+        assert_close(&decoded.samples, &tone, 1e-6);
+        let decoded = decode(&wav(&tone, 48000, 1, WavEncoding::Float32), &RAW).unwrap();
+        assert_close(&decoded.samples, &tone, 0.0);
+    }
+
+    #[test]
+    fn keeps_wav_channels_interleaved() {
+        let stereo = interleave(&[sine(440.0, 0.05, 16000), vec![0.25; 800]]);
+        let decoded = decode(&wav(&stereo, 16000, 2, WavEncoding::Float32), &RAW).unwrap();
+        assert_eq!(decoded.channels, 2);
+        assert_close(&decoded.samples, &stereo, 0.0);
+    }
+
+    #[test]
+    fn reads_streamed_wav_with_unset_sizes() {
+        let tone = sine(440.0, 0.05, 16000);
+        let mut bytes = wav(&tone, 16000, 1, WavEncoding::Pcm16);
+        bytes[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        bytes[40..44].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(decode(&bytes, &RAW).unwrap().samples.len(), tone.len());
+    }
+
+    #[test]
+    fn skips_unknown_chunks() {
+        let tone = sine(440.0, 0.01, 16000);
+        let bytes = wav(&tone, 16000, 1, WavEncoding::Pcm16);
+        // Insert an odd-sized LIST chunk (with its pad byte) between fmt and data.
+        let mut with_list = bytes[..36].to_vec();
+        with_list.extend_from_slice(b"LIST");
+        with_list.extend_from_slice(&3u32.to_le_bytes());
+        with_list.extend_from_slice(&[1, 2, 3, 0]);
+        with_list.extend_from_slice(&bytes[36..]);
+        assert_eq!(decode(&with_list, &RAW).unwrap().samples.len(), tone.len());
+    }
+
+    #[test]
+    fn rejects_wav_without_data() {
+        let bytes = wav(&[], 16000, 1, WavEncoding::Pcm16);
+        assert!(decode(&bytes[..36], &RAW).is_err());
+    }
+
+    #[test]
+    fn encoded_wav_round_trips() {
+        let tone = sine(440.0, 0.1, 16000);
+        let decoded = decode(&encode_wav(&tone, 16000), &RAW).unwrap();
+        assert_eq!((decoded.rate, decoded.channels), (16000, 1));
+        assert_close(&decoded.samples, &tone, 1e-4);
+    }
+
+    #[test]
+    fn decodes_raw_pcm() {
+        let bytes: Vec<u8> = [0i16, i16::MAX, -i16::MAX]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        let decoded = decode(&bytes, &RAW).unwrap();
+        assert_eq!(decoded.samples, vec![0.0, 1.0, -1.0]);
+
+        let raw = RawPcm {
+            rate: 8000,
+            channels: 2,
+            format: PcmFormat::F32le,
+        };
+        let bytes: Vec<u8> = [0.5f32, -0.5]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        let decoded = decode(&bytes, &raw).unwrap();
+        assert_eq!((decoded.rate, decoded.channels), (8000, 2));
+        assert_eq!(decoded.samples, vec![0.5, -0.5]);
+    }
+
+    #[test]
+    fn rejects_zero_sample_rate() {
+        let mut bytes = wav(&[0.0; 16], 16000, 1, WavEncoding::Pcm16);
+        bytes[24..28].copy_from_slice(&0u32.to_le_bytes());
+        assert!(decode(&bytes, &RAW).is_err());
+        let raw = RawPcm { rate: 0, ..RAW };
+        assert!(decode(&[0; 8], &raw).is_err());
+    }
+
+    #[test]
+    fn drops_partial_raw_frame() {
+        let raw = RawPcm {
+            rate: 16000,
+            channels: 2,
+            format: PcmFormat::S16le,
+        };
+        // Two full stereo frames plus one sample and a stray byte of a third.
+        let bytes: Vec<u8> = [1i16, 2, 3, 4, 5]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .chain([0])
+            .collect();
+        assert_eq!(decode_raw(&bytes, &raw).unwrap().samples.len(), 4);
+    }
+}
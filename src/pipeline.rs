@@ -0,0 +1,174 @@
+use crate::backend::TranscriptionBackend;
+use crate::postprocess::PostProcess;
+use crate::{analyze, audio, transcribe, vad};
+use anyhow::Result;
+use std::io::Write;
+
+/// Transcripts below this mean token confidence get level advice logged with them.
+const LOW_CONFIDENCE: f32 = 0.5;
+
+/// A backend used when the primary transcript's confidence is below `below`.
+pub struct Fallback {
+    pub backend: Box<dyn TranscriptionBackend>,
+    pub below: f32,
+}
+
+/// Turns 16kHz mono samples into final text: speech detection, Whisper and
+/// post-processing. Shared by push-to-talk and the one-shot subcommands.
+pub struct Pipeline {
+    pub backend: Box<dyn TranscriptionBackend>,
+    /// Second backend for transcripts the first one isn't confident about.
+    pub fallback: Option<Fallback>,
+    /// High-pass cutoff in Hz for [`audio::remove_rumble`], or `None` to leave the
+    /// audio unfiltered.
+    pub highpass: Option<f32>,
+    pub speech_detector: Option<vad::SpeechDetector>,
+    pub opts: transcribe::TranscribeOptions,
+    pub post: PostProcess,
+    /// Write each transcript's tokens to stdout as TSV.
+    pub print_tokens: bool,
+}
+
+impl Pipeline {
+    /// Transcribe `samples`, logging progress to stderr. Returns `None` when there
+    /// was no speech or the transcript came out empty.
+    pub fn process(&mut self, mut samples: Vec<f32>) -> Result<Option<String>> {
+        if let Some(cutoff) = self.highpass {
+            audio::remove_rumble(&mut samples, cutoff);
+        }
+        let levels = analyze::levels(&samples);
+        eprintln!("[stt-typer] recording: {levels}");
+        let advise = || {
+            if let Some(advice) = levels.advice() {
+                eprintln!("[stt-typer] {advice}");
+            }
+        };
+
+        let samples = match self
+            .speech_detector
+            .as_mut()
+            .map(|d| d.speech_only(&samples))
+        {
+            Some(Ok(speech)) if speech.is_empty() => {
+                eprintln!("[stt-typer] no speech detected, skipping");
+                advise();
+                return Ok(None);
+            }
+            Some(Ok(speech)) => {
+                eprintln!(
+                    "[stt-typer] VAD kept {:.1}s of speech",
+                    speech.len() as f32 / 16000.0
+                );
+                speech
+            }
+            Some(Err(e)) => {
+                eprintln!("[stt-typer] {e:#}, transcribing unfiltered audio");
+                samples
+            }
+            None => samples,
+        };
+
+        let mut transcript = self.backend.transcribe(&samples, &self.opts)?;
+        if let Some(fallback) = &mut self.fallback
+            && !transcript.text.is_empty()
+            && transcript.confidence < fallback.below
+        {
+            eprintln!(
+                "[stt-typer] confidence {:.2} below {:.2}, using fallback backend",
+                transcript.confidence, fallback.below
+            );
+            // Keep the fallback within --allowed-languages by pinning the language
+            // the local pass detected and coerced.
+            let opts = if self.opts.language == "auto" && !self.opts.allowed_languages.is_empty() {
+                transcribe::TranscribeOptions {
+                    language: transcript.language.clone(),
+                    ..self.opts.clone()
+                }
+            } else {
+                self.opts.clone()
+            };
+            match fallback.backend.transcribe(&samples, &opts) {
+                Ok(better) => transcript = better,
+                Err(e) => eprintln!("[stt-typer] fallback failed, keeping local transcript: {e:#}"),
+            }
+        }
+        if transcript.retried {
+            eprintln!(
+                "[stt-typer] low confidence, re-decoded with beam search (confidence {:.2})",
+                transcript.confidence
+            );
+        }
+        if transcript.hallucinations_removed {
+            eprintln!("[stt-typer] stripped blocklisted phrases from non-speech segments");
+        }
+        if transcript.loop_removed {
+            eprintln!("[stt-typer] removed a repetition loop from the transcript");
+        }
+        if self.print_tokens {
+            print_tokens(&transcript.tokens);
+        }
+        if !transcript.text.is_empty() && transcript.confidence < LOW_CONFIDENCE {
+            advise();
+        }
+        let raw = transcript.text;
+
+        let text = self.post.apply(&raw, &transcript.language);
+        // Keep the unprocessed Whisper output auditable when post-processing changed it.
+        if text != raw {
+            eprintln!("[stt-typer] raw transcript: {raw}");
+            eprintln!("[stt-typer] processed:      {text}");
+        }
+
+        if text.is_empty() {
+            eprintln!("[stt-typer] (empty transcription)");
+            advise();
+            return Ok(None);
+        }
+        Ok(Some(text))
+    }
+}
+
+/// Write tokens to stdout, see [`write_tokens`].
+fn print_tokens(tokens: &[transcribe::Token]) {
+    let mut out = std::io::stdout().lock();
+    let _ = write_tokens(&mut out, tokens);
+    let _ = out.flush();
+}
+
+/// Write tokens as tab-separated `start_ms end_ms probability text` lines,
+/// followed by a blank line to separate utterances.
+fn write_tokens(out: &mut impl Write, tokens: &[transcribe::Token]) -> std::io::Result<()> {
+    for token in tokens {
+        writeln!(
+            out,
+            "{}\t{}\t{:.4}\t{}",
+            token.start_ms, token.end_ms, token.probability, token.text
+        )?;
+    }
+    writeln!(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_written_as_tsv() {
+        let token = |text: &str, start_ms, end_ms, probability| transcribe::Token {
+            text: text.to_string(),
+            start_ms,
+            end_ms,
+            probability,
+        };
+        let mut out = Vec::new();
+        write_tokens(
+            &mut out,
+            &[token(" Hello", 0, 320, 0.98761), token(",", 320, 400, 0.5)],
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "0\t320\t0.9876\t Hello\n320\t400\t0.5000\t,\n\n"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! Transcript post-processing as an ordered list of steps (`--postprocess`):
+//! cleanup, number normalization and the replacement dictionary, each run on
+//! the previous step's output.
+
+use anyhow::{Result, bail};
+use clap::ValueEnum;
+
+use crate::{cleanup, itn, replace};
+
+/// Post-processing steps that can be listed in `--postprocess`.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum Step {
+    /// Filler removal and sentence casing (`--filler-words`)
+    Cleanup,
+    /// Spoken numbers and dates to digits (`--number-locale`)
+    Numbers,
+    /// The `--replacements` dictionary
+    Replace,
+}
+
+impl Step {
+    /// The name as typed on the command line.
+    fn name(self) -> String {
+        self.to_possible_value()
+            .map_or_else(|| format!("{self:?}"), |v| v.get_name().to_string())
+    }
+}
+
+/// A configured step.
+pub enum Stage {
+    /// Filler words for [`cleanup::cleanup`].
+    Cleanup(Vec<String>),
+    /// Written conventions for [`itn::normalize`]; `None` follows the transcript's language.
+    Numbers(Option<Box<itn::Locale>>),
+    Replace(replace::Replacements),
+}
+
+/// Text post-processing applied to every transcript, stage by stage in order.
+#[derive(Default)]
+pub struct PostProcess {
+    pub stages: Vec<Stage>,
+}
+
+impl PostProcess {
+    /// Configure `steps` in order. `fillers`, `locale` and `replacements` come from
+    /// `--filler-words`, `--number-locale` and `--replacements`; a step listed twice,
+    /// `replace` without replacements, or replacements without a `replace` step is an error.
+    pub fn build(
+        steps: &[Step],
+        fillers: &[String],
+        locale: Option<itn::Locale>,
+        mut replacements: Option<replace::Replacements>,
+    ) -> Result<Self> {
+        let mut stages = Vec::new();
+        for (i, &step) in steps.iter().enumerate() {
+            if steps[..i].contains(&step) {
+                bail!("--postprocess lists {} more than once", step.name());
+            }
+            stages.push(match step {
+                Step::Cleanup => Stage::Cleanup(fillers.to_vec()),
+                Step::Numbers => Stage::Numbers(locale.map(Box::new)),
+                Step::Replace => match replacements.take() {
+                    Some(replacements) => Stage::Replace(replacements),
+                    None => bail!("the replace post-processing step needs --replacements FILE"),
+                },
+            });
+        }
+        if replacements.is_some() {
+            bail!("--replacements is given but --postprocess has no replace step");
+        }
+        Ok(PostProcess { stages })
+    }
+
+    /// Run every stage over `text`. `language` is the transcript's language code.
+    pub fn apply(&self, text: &str, language: &str) -> String {
+        self.stages
+            .iter()
+            .fold(text.to_string(), |text, stage| match stage {
+                Stage::Cleanup(fillers) => cleanup::cleanup(&text, fillers),
+                Stage::Numbers(Some(locale)) => itn::normalize(&text, locale),
+                Stage::Numbers(None) => itn::normalize(&text, &itn::Locale::for_language(language)),
+                Stage::Replace(replacements) => replacements.apply(&text),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replacements(rules: &str) -> Stage {
+        Stage::Replace(replace::Replacements::parse(rules).unwrap())
+    }
+
+    fn build(steps: &[Step], replacements: Option<&str>) -> Result<PostProcess> {
+        let replacements = replacements.map(|rules| replace::Replacements::parse(rules).unwrap());
+        PostProcess::build(steps, &["um".to_string()], None, replacements)
+    }
+
+    #[test]
+    fn build_configures_listed_steps_from_the_flags() {
+        let post = build(
+            &[Step::Replace, Step::Cleanup],
+            Some("cube control = kubectl"),
+        )
+        .unwrap();
+        assert!(matches!(
+            post.stages[..],
+            [Stage::Replace(_), Stage::Cleanup(_)]
+        ));
+        assert_eq!(post.apply("um run cube control", "en"), "Run kubectl.");
+
+        let numbers = build(&[Step::Numbers], None).unwrap();
+        assert_eq!(numbers.apply("um twenty one", "en"), "um 21");
+    }
+
+    #[test]
+    fn build_rejects_unused_or_missing_replacements() {
+        let unused = build(&[Step::Cleanup, Step::Numbers], Some("a = b"))
+            .err()
+            .unwrap();
+        assert!(unused.to_string().contains("no replace step"), "{unused}");
+
+        let missing = build(&[Step::Replace], None).err().unwrap();
+        assert!(
+            missing.to_string().contains("--replacements FILE"),
+            "{missing}"
+        );
+    }
+
+    #[test]
+    fn build_names_a_repeated_step_as_typed() {
+        let err = build(&[Step::Numbers, Step::Cleanup, Step::Numbers], None)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "--postprocess lists numbers more than once"
+        );
+    }
+
+    #[test]
+    fn no_stages_leaves_text_alone() {
+        assert_eq!(
+            PostProcess::default().apply("um hello there", "en"),
+            "um hello there"
+        );
+    }
+
+    #[test]
+    fn stages_run_in_listed_order() {
+        // Replacing before normalization sees the spoken words; after, the digits.
+        let before = PostProcess {
+            stages: vec![replacements("twenty = thirty"), Stage::Numbers(None)],
+        };
+        assert_eq!(before.apply("twenty one", "en"), "31");
+
+        let after = PostProcess {
+            stages: vec![Stage::Numbers(None), replacements("twenty = thirty")],
+        };
+        assert_eq!(after.apply("twenty one", "en"), "21");
+    }
+
+    #[test]
+    fn cleanup_then_replacements() {
+        let post = PostProcess {
+            stages: vec![
+                Stage::Cleanup(vec!["um".to_string()]),
+                replacements("cube control = kubectl"),
+            ],
+        };
+        assert_eq!(post.apply("um run cube control", "en"), "Run kubectl.");
+    }
+}
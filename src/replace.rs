@@ -0,0 +1,114 @@
+//! User replacement dictionary (`--replacements`): rewrites misheard phrases
+//! ("cube control") to the intended spelling ("kubectl") on whole-word matches.
+
+use crate::words::key;
+use anyhow::{Result, bail};
+
+/// Ordered replacement rules, longest phrase first.
+pub struct Replacements {
+    rules: Vec<(Vec<String>, String)>,
+}
+
+impl Replacements {
+    /// Parse `spoken phrase = replacement` lines. Blank lines and `#` comments are
+    /// ignored; the replacement may be empty to delete the phrase.
+    pub fn parse(contents: &str) -> Result<Self> {
+        let mut rules = Vec::new();
+        for (n, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((from, to)) = line.split_once('=') else {
+                bail!("line {}: expected \"spoken phrase = replacement\"", n + 1);
+            };
+            let keys: Vec<String> = from
+                .split_whitespace()
+                .map(key)
+                .filter(|k| !k.is_empty())
+                .collect();
+            if keys.is_empty() {
+                bail!("line {}: empty phrase before \"=\"", n + 1);
+            }
+            rules.push((keys, to.trim().to_string()));
+        }
+        // Longest first, so "post gress sql" wins over "post gress".
+        rules.sort_by_key(|(keys, _)| std::cmp::Reverse(keys.len()));
+        Ok(Replacements { rules })
+    }
+
+    /// Apply the replacements to `text`. Punctuation around a matched phrase is kept.
+    pub fn apply(&self, text: &str) -> String {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let keys: Vec<String> = words.iter().map(|w| key(w)).collect();
+
+        let mut out: Vec<String> = Vec::with_capacity(words.len());
+        let mut i = 0;
+        'words: while i < words.len() {
+            for (phrase, replacement) in &self.rules {
+                if keys[i..].starts_with(phrase) {
+                    let first = words[i];
+                    let last = words[i + phrase.len() - 1];
+                    let not_word = |c: char| !c.is_alphanumeric();
+                    let lead = &first[..first.len() - first.trim_start_matches(not_word).len()];
+                    let trail = &last[last.trim_end_matches(not_word).len()..];
+                    let word = format!("{lead}{replacement}{trail}");
+                    if !word.is_empty() {
+                        out.push(word);
+                    }
+                    i += phrase.len();
+                    continue 'words;
+                }
+            }
+            out.push(words[i].to_string());
+            i += 1;
+        }
+        out.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(rules: &str, text: &str) -> String {
+        Replacements::parse(rules).unwrap().apply(text)
+    }
+
+    #[test]
+    fn matches_whole_words_only() {
+        let rules = "cube control = kubectl";
+        assert_eq!(
+            apply(rules, "run cube control get pods"),
+            "run kubectl get pods"
+        );
+        assert_eq!(apply(rules, "the cube controller"), "the cube controller");
+        assert_eq!(apply(rules, "a cube"), "a cube");
+    }
+
+    #[test]
+    fn ignores_case_and_keeps_surrounding_punctuation() {
+        let rules = "post gress = Postgres";
+        assert_eq!(apply(rules, "Post Gress, please."), "Postgres, please.");
+        assert_eq!(apply(rules, "(post gress)"), "(Postgres)");
+    }
+
+    #[test]
+    fn longest_phrase_wins() {
+        let rules = "post gress = Postgres\npost gress sequel = PostgreSQL";
+        assert_eq!(apply(rules, "use post gress sequel"), "use PostgreSQL");
+        assert_eq!(apply(rules, "use post gress"), "use Postgres");
+    }
+
+    #[test]
+    fn empty_replacement_deletes_phrase() {
+        assert_eq!(apply("you know =", "it is you know fine"), "it is fine");
+    }
+
+    #[test]
+    fn parse_skips_comments_and_rejects_bad_lines() {
+        assert_eq!(apply("# comment\n\nget hub = GitHub", "get hub"), "GitHub");
+        assert!(Replacements::parse("no equals sign").is_err());
+        assert!(Replacements::parse(" = orphan").is_err());
+    }
+}
@@ -0,0 +1,234 @@
+//! First-run setup (`stt-typer setup`): checks permissions and ydotool, picks an
+//! input device, downloads a Whisper model sized for the machine and prints the
+//! command line to use.
+
+use crate::{audio, keyboard};
+use anyhow::{Context, Result, bail};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where ggml models are downloaded from.
+const MODEL_URL: &str = "https://huggingface.co/ggerganov/whisper.cpp/resolve/main";
+
+/// Whisper model sizes offered by setup, smallest first.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ModelSize {
+    Tiny,
+    Base,
+    Small,
+    Medium,
+}
+
+impl ModelSize {
+    fn file_name(self) -> &'static str {
+        match self {
+            ModelSize::Tiny => "ggml-tiny.bin",
+            ModelSize::Base => "ggml-base.bin",
+            ModelSize::Small => "ggml-small.bin",
+            ModelSize::Medium => "ggml-medium.bin",
+        }
+    }
+
+    /// The largest model that stays responsive on this machine, judged by total
+    /// RAM and CPU count. Defaults to `base` if RAM can't be read.
+    fn recommended() -> Self {
+        let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let size = match total_memory_gib() {
+            Some(gib) if gib < 4.0 => ModelSize::Tiny,
+            Some(gib) if gib < 8.0 => ModelSize::Base,
+            Some(gib) if gib < 16.0 => ModelSize::Small,
+            Some(_) => ModelSize::Medium,
+            None => ModelSize::Base,
+        };
+        // Larger models are too slow for push-to-talk on few cores.
+        match (size, cpus) {
+            (ModelSize::Small | ModelSize::Medium, 0..=3) => ModelSize::Base,
+            (ModelSize::Medium, 4..=7) => ModelSize::Small,
+            _ => size,
+        }
+    }
+}
+
+/// What the user chose during setup.
+pub struct Choice {
+    pub model: PathBuf,
+    /// Input devices to capture from; empty means the system default.
+    pub devices: Vec<String>,
+}
+
+/// Total system memory from `/proc/meminfo`, in GiB.
+fn total_memory_gib() -> Option<f64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|l| l.starts_with("MemTotal:"))?;
+    let kib: f64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib / (1024.0 * 1024.0))
+}
+
+/// Ask a question on stderr and read one trimmed line from stdin.
+fn prompt(question: &str) -> Result<String> {
+    eprint!("{question}");
+    std::io::stderr().flush()?;
+    let mut line = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .context("failed to read answer")?;
+    Ok(line.trim().to_string())
+}
+
+/// Run the setup steps. Non-empty `devices` or a `size` skip the corresponding
+/// questions, and an existing `model` skips choosing and downloading one; with `assume_yes` every question takes its default answer.
+pub fn run(
+    model_dir: &Path,
+    model: Option<&Path>,
+    hotkey: &keyboard::Hotkey,
+    devices: &[String],
+    size: Option<ModelSize>,
+    assume_yes: bool,
+) -> Result<Choice> {
+    check_environment(hotkey);
+
+    let devices = if !devices.is_empty() {
+        audio::check_input_devices(devices)?;
+        devices.to_vec()
+    } else if assume_yes {
+        Vec::new()
+    } else {
+        pick_device()?.into_iter().collect()
+    };
+
+    // An explicit --model is used as is instead of picking and downloading one.
+    if let Some(model) = model {
+        if size.is_some() {
+            bail!("--model-size picks a model to download; drop it or --model");
+        }
+        if !model.exists() {
+            bail!("model not found at {}", model.display());
+        }
+        eprintln!("[stt-typer] using model {}", model.display());
+        return Ok(Choice {
+            model: model.to_path_buf(),
+            devices,
+        });
+    }
+
+    let recommended = ModelSize::recommended();
+    let size = match size {
+        Some(size) => size,
+        None if assume_yes => recommended,
+        None => pick_model(recommended)?,
+    };
+
+    let model = model_dir.join(size.file_name());
+    if model.exists() {
+        eprintln!("[stt-typer] using existing model {}", model.display());
+    } else {
+        download(size, &model)?;
+    }
+
+    Ok(Choice { model, devices })
+}
+
+/// Report problems that would stop push-to-talk with `hotkey` from working.
+fn check_environment(hotkey: &keyboard::Hotkey) {
+    if keyboard::find_keyboard_devices(hotkey).is_empty() {
+        eprintln!("[stt-typer] warning: push-to-talk needs access to /dev/input (see above)");
+    } else {
+        eprintln!("[stt-typer] keyboard access: ok");
+    }
+    match Command::new("ydotool").arg("help").output() {
+        Ok(_) => eprintln!("[stt-typer] ydotool: ok"),
+        Err(_) => eprintln!(
+            "[stt-typer] warning: ydotool not found; install it to type transcripts \
+             (sudo dnf install ydotool && sudo systemctl enable --now ydotool)"
+        ),
+    }
+}
+
+fn pick_device() -> Result<Option<String>> {
+    let devices = audio::list_input_devices()?;
+    if devices.is_empty() {
+        bail!("no audio input devices found");
+    }
+    eprintln!("\nInput devices:");
+    eprintln!("  0) system default");
+    for (i, name) in devices.iter().enumerate() {
+        eprintln!("  {}) {name}", i + 1);
+    }
+    loop {
+        let answer = prompt("Microphone to use [0]: ")?;
+        match answer.parse::<usize>() {
+            _ if answer.is_empty() => return Ok(None),
+            Ok(0) => return Ok(None),
+            Ok(n) if n <= devices.len() => return Ok(Some(devices[n - 1].clone())),
+            _ => eprintln!("Enter a number from the list."),
+        }
+    }
+}
+
+fn pick_model(recommended: ModelSize) -> Result<ModelSize> {
+    let sizes = [
+        (ModelSize::Tiny, "tiny", "75 MB, fastest, least accurate"),
+        (ModelSize::Base, "base", "142 MB, good for most dictation"),
+        (
+            ModelSize::Small,
+            "small",
+            "466 MB, more accurate, needs a fast CPU",
+        ),
+        (
+            ModelSize::Medium,
+            "medium",
+            "1.5 GB, most accurate, slow without a GPU",
+        ),
+    ];
+    let default = sizes
+        .iter()
+        .position(|(s, _, _)| *s == recommended)
+        .unwrap_or(1);
+    eprintln!("\nWhisper models:");
+    for (i, (_, name, about)) in sizes.iter().enumerate() {
+        let marker = if i == default {
+            " (recommended for this machine)"
+        } else {
+            ""
+        };
+        eprintln!("  {}) {name}: {about}{marker}", i + 1);
+    }
+    loop {
+        let answer = prompt(&format!("Model to use [{}]: ", default + 1))?;
+        match answer.parse::<usize>() {
+            _ if answer.is_empty() => return Ok(sizes[default].0),
+            Ok(n) if (1..=sizes.len()).contains(&n) => return Ok(sizes[n - 1].0),
+            _ => eprintln!("Enter a number from the list."),
+        }
+    }
+}
+
+/// Download a model with curl, via a temporary file so an interrupted download
+/// never leaves a truncated model behind.
+fn download(size: ModelSize, dest: &Path) -> Result<()> {
+    if let Some(dir) = dest.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    let url = format!("{MODEL_URL}/{}", size.file_name());
+    let partial = dest.with_extension("bin.part");
+    eprintln!("[stt-typer] downloading {url}");
+    let status = Command::new("curl")
+        .arg("-fL")
+        .arg("--progress-bar")
+        .arg("-o")
+        .arg(&partial)
+        .arg(&url)
+        .status()
+        .context("failed to run curl")?;
+    if !status.success() {
+        let _ = std::fs::remove_file(&partial);
+        bail!("model download failed ({status})");
+    }
+    std::fs::rename(&partial, dest)
+        .with_context(|| format!("failed to move model to {}", dest.display()))?;
+    eprintln!("[stt-typer] saved model to {}", dest.display());
+    Ok(())
+}
@@ -0,0 +1,74 @@
+//! Generated audio fixtures for unit tests: tones and in-memory WAV files, so
+//! tests don't depend on binary files in the repository.
+
+/// `secs` of a sine wave at `freq` Hz and amplitude 0.5, sampled at `rate`.
+pub fn sine(freq: f32, secs: f32, rate: u32) -> Vec<f32> {
+    let n = (secs * rate as f32) as usize;
+    (0..n)
+        .map(|i| 0.5 * (2.0 * std::f32::consts::PI * freq * i as f32 / rate as f32).sin())
+        .collect()
+}
+
+/// Interleave per-channel tracks of equal length into one buffer.
+pub fn interleave(channels: &[Vec<f32>]) -> Vec<f32> {
+    let len = channels.iter().map(Vec::len).min().unwrap_or(0);
+    (0..len)
+        .flat_map(|i| channels.iter().map(move |c| c[i]))
+        .collect()
+}
+
+/// Estimate the frequency of a tone from its rising zero crossings.
+pub fn zero_crossing_freq(samples: &[f32], rate: u32) -> f32 {
+    let crossings = samples
+        .windows(2)
+        .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+        .count();
+    crossings as f32 * rate as f32 / samples.len() as f32
+}
+
+/// Sample encodings [`wav`] can write.
+#[derive(Clone, Copy)]
+pub enum WavEncoding {
+    Pcm16,
+    Pcm24,
+    Float32,
+}
+
+/// Encode interleaved samples as a RIFF/WAVE file.
+pub fn wav(samples: &[f32], rate: u32, channels: u16, encoding: WavEncoding) -> Vec<u8> {
+    let (tag, bits): (u16, u16) = match encoding {
+        WavEncoding::Pcm16 => (1, 16),
+        WavEncoding::Pcm24 => (1, 24),
+        WavEncoding::Float32 => (3, 32),
+    };
+    let mut data = Vec::new();
+    for &s in samples {
+        match encoding {
+            WavEncoding::Pcm16 => {
+                data.extend_from_slice(&((s * i16::MAX as f32) as i16).to_le_bytes())
+            }
+            WavEncoding::Pcm24 => {
+                data.extend_from_slice(&((s * 8_388_607.0) as i32).to_le_bytes()[..3])
+            }
+            WavEncoding::Float32 => data.extend_from_slice(&s.to_le_bytes()),
+        }
+    }
+
+    let block_align = channels * bits / 8;
+    let mut out = Vec::with_capacity(44 + data.len());
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+    out.extend_from_slice(b"WAVE");
+    out.extend_from_slice(b"fmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&tag.to_le_bytes());
+    out.extend_from_slice(&channels.to_le_bytes());
+    out.extend_from_slice(&rate.to_le_bytes());
+    out.extend_from_slice(&(rate * block_align as u32).to_le_bytes());
+    out.extend_from_slice(&block_align.to_le_bytes());
+    out.extend_from_slice(&bits.to_le_bytes());
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(&data);
+    out
+}
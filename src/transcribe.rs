@@ -1,34 +1,280 @@
-use anyhow::{Context, Result};
+use crate::{hallucination, words};
+use anyhow::{Context, Result, bail};
 use std::path::Path;
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+use whisper_rs::{
+    DtwMode, DtwModelPreset, DtwParameters, FullParams, SamplingStrategy, WhisperContext,
+    WhisperContextParameters, WhisperState,
+};
+
+/// Alignment-head preset for DTW token timestamps. Must match the loaded model.
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum DtwPreset {
+    #[value(name = "tiny.en")]
+    TinyEn,
+    Tiny,
+    #[value(name = "base.en")]
+    BaseEn,
+    Base,
+    #[value(name = "small.en")]
+    SmallEn,
+    Small,
+    #[value(name = "medium.en")]
+    MediumEn,
+    Medium,
+    #[value(name = "large-v1")]
+    LargeV1,
+    #[value(name = "large-v2")]
+    LargeV2,
+    #[value(name = "large-v3")]
+    LargeV3,
+    #[value(name = "large-v3-turbo")]
+    LargeV3Turbo,
+}
+
+impl DtwPreset {
+    fn model_preset(self) -> DtwModelPreset {
+        match self {
+            DtwPreset::TinyEn => DtwModelPreset::TinyEn,
+            DtwPreset::Tiny => DtwModelPreset::Tiny,
+            DtwPreset::BaseEn => DtwModelPreset::BaseEn,
+            DtwPreset::Base => DtwModelPreset::Base,
+            DtwPreset::SmallEn => DtwModelPreset::SmallEn,
+            DtwPreset::Small => DtwModelPreset::Small,
+            DtwPreset::MediumEn => DtwModelPreset::MediumEn,
+            DtwPreset::Medium => DtwModelPreset::Medium,
+            DtwPreset::LargeV1 => DtwModelPreset::LargeV1,
+            DtwPreset::LargeV2 => DtwModelPreset::LargeV2,
+            DtwPreset::LargeV3 => DtwModelPreset::LargeV3,
+            DtwPreset::LargeV3Turbo => DtwModelPreset::LargeV3Turbo,
+        }
+    }
+}
 
 /// Create a WhisperContext from a model file, reusable across multiple transcriptions.
-pub fn create_context(model_path: &Path) -> Result<WhisperContext> {
-    WhisperContext::new_with_params(
-        model_path.to_str().unwrap_or_default(),
-        WhisperContextParameters::default(),
-    )
-    .context("failed to load whisper model")
+/// With `dtw` set, whisper.cpp aligns each token against the audio using the preset's
+/// attention heads, giving much more accurate token timestamps.
+pub fn create_context(model_path: &Path, dtw: Option<DtwPreset>) -> Result<WhisperContext> {
+    let mut params = WhisperContextParameters::default();
+    if let Some(preset) = dtw {
+        params.dtw_parameters(DtwParameters {
+            mode: DtwMode::ModelPreset {
+                model_preset: preset.model_preset(),
+            },
+            ..DtwParameters::default()
+        });
+    }
+    WhisperContext::new_with_params(model_path.to_str().unwrap_or_default(), params)
+        .context("failed to load whisper model")
+}
+
+/// Check that `language` is a code Whisper knows, or "auto".
+pub fn validate_language(language: &str) -> Result<()> {
+    if language != "auto" && whisper_rs::get_lang_id(language).is_none() {
+        bail!("unknown language code \"{language}\"");
+    }
+    Ok(())
+}
+
+/// Beam width used when re-decoding a low-confidence transcript.
+const RETRY_BEAM_SIZE: i32 = 5;
+
+/// A phrase repeated at least this many times in a row is treated as a decoder loop.
+const LOOP_MIN_REPEATS: usize = 3;
+/// ...provided the repeats span at least this many words, so "no no no" survives.
+const LOOP_MIN_WORDS: usize = 8;
+/// Longest phrase, in words, checked for repetition.
+const LOOP_MAX_NGRAM: usize = 30;
+
+/// Decoding options applied to every transcription.
+#[derive(Clone)]
+pub struct TranscribeOptions {
+    /// Language code, or "auto" to detect it.
+    pub language: String,
+    /// If non-empty, "auto" detection is coerced to the most probable of these.
+    pub allowed_languages: Vec<String>,
+    /// Re-decode with beam search when confidence falls below this (0–1).
+    pub retry_below: Option<f32>,
+    /// Collect per-token text, timestamps and probabilities into [`Transcript::tokens`].
+    pub tokens: bool,
+    /// Phrases stripped from probable non-speech segments; `None` uses the
+    /// built-in list for the transcript's language.
+    pub hallucinations: Option<Vec<String>>,
+    /// No-speech probability (0–1) above which a segment is checked against the blocklist.
+    pub no_speech_thold: f32,
+    /// Re-decode a segment at higher temperature when its token entropy falls below
+    /// this (whisper.cpp's compression-ratio check). Higher catches more repetition.
+    pub entropy_thold: f32,
+    /// Re-decode a segment at higher temperature when its average log probability
+    /// falls below this.
+    pub logprob_thold: f32,
+    /// Don't let a segment start with a blank token (whisper.cpp default: on).
+    pub suppress_blank: bool,
+    /// Suppress non-speech tokens such as "[Music]" or "(coughs)".
+    pub suppress_non_speech: bool,
+}
+
+/// A decoded text token with its timing and probability.
+pub struct Token {
+    pub text: String,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub probability: f32,
+}
+
+/// The result of a transcription.
+pub struct Transcript {
+    pub text: String,
+    /// Language the audio was transcribed as (the detected one for "auto").
+    pub language: String,
+    /// Mean probability of the decoded text tokens (0–1).
+    pub confidence: f32,
+    /// Whether a low-confidence first pass was re-decoded with beam search.
+    pub retried: bool,
+    /// Text tokens, only populated when [`TranscribeOptions::tokens`] is set.
+    pub tokens: Vec<Token>,
+    /// Whether a repetition loop was cut out of the text.
+    pub loop_removed: bool,
+    /// Whether blocklisted hallucinations were stripped from the text.
+    pub hallucinations_removed: bool,
 }
 
-/// Transcribe audio using an existing WhisperContext.
-pub fn transcribe_with_context(ctx: &WhisperContext, audio: &[f32], language: &str) -> Result<String> {
-    let mut state = ctx.create_state().context("failed to create whisper state")?;
+/// Transcribe audio reusing a state created from `ctx`. Each full pass resets the
+/// state's results, so one state can serve any number of sequential recordings.
+///
+/// If `opts.language` is "auto" and `opts.allowed_languages` is non-empty, the spoken
+/// language is detected first and coerced to the most probable allowed language.
+pub fn transcribe_with_state(
+    ctx: &WhisperContext,
+    state: &mut WhisperState,
+    audio: &[f32],
+    opts: &TranscribeOptions,
+) -> Result<Transcript> {
+    let language = if opts.language == "auto" && !opts.allowed_languages.is_empty() {
+        detect_allowed_language(state, audio, &opts.allowed_languages)?
+    } else {
+        opts.language.clone()
+    };
+
+    let greedy = SamplingStrategy::Greedy { best_of: 1 };
+    let first = decode(ctx, state, audio, &language, greedy, opts)?;
+
+    if let Some(threshold) = opts.retry_below
+        && !first.text.is_empty()
+        && first.confidence < threshold
+    {
+        let beam = SamplingStrategy::BeamSearch {
+            beam_size: RETRY_BEAM_SIZE,
+            patience: -1.0,
+        };
+        let retry = decode(ctx, state, audio, &language, beam, opts)?;
+        // Keep whichever pass Whisper was more sure of.
+        let best = if retry.confidence >= first.confidence {
+            retry
+        } else {
+            first
+        };
+        return Ok(remove_loops(Transcript {
+            retried: true,
+            ..best
+        }));
+    }
+
+    Ok(remove_loops(first))
+}
 
-    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+/// Cut decoder repetition loops (the same phrase over and over, typical after a
+/// long silence) down to a single copy of the phrase.
+fn remove_loops(transcript: Transcript) -> Transcript {
+    match collapse_repetition(&transcript.text) {
+        Some(text) => Transcript {
+            text,
+            loop_removed: true,
+            ..transcript
+        },
+        None => transcript,
+    }
+}
+
+/// Collapse each run of a phrase repeated back to back into one copy. Words are
+/// compared case-insensitively, ignoring punctuation. Returns `None` if nothing
+/// looked like a loop.
+fn collapse_repetition(text: &str) -> Option<String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let keys: Vec<String> = words.iter().map(|w| words::key(w)).collect();
+
+    let mut out: Vec<&str> = Vec::with_capacity(words.len());
+    let mut changed = false;
+    let mut i = 0;
+    while i < words.len() {
+        let max_n = LOOP_MAX_NGRAM.min((words.len() - i) / LOOP_MIN_REPEATS);
+        let run = (1..=max_n).find_map(|n| {
+            let phrase = &keys[i..i + n];
+            let mut repeats = 1;
+            while i + (repeats + 1) * n <= keys.len()
+                && &keys[i + repeats * n..i + (repeats + 1) * n] == phrase
+            {
+                repeats += 1;
+            }
+            (repeats >= LOOP_MIN_REPEATS && repeats * n >= LOOP_MIN_WORDS).then_some((n, repeats))
+        });
+        match run {
+            Some((n, repeats)) => {
+                out.extend_from_slice(&words[i..i + n]);
+                i += n * repeats;
+                changed = true;
+            }
+            None => {
+                out.push(words[i]);
+                i += 1;
+            }
+        }
+    }
+
+    changed.then(|| out.join(" "))
+}
+
+/// Run a full Whisper pass and return the text with its mean token probability.
+fn decode(
+    ctx: &WhisperContext,
+    state: &mut WhisperState,
+    audio: &[f32],
+    language: &str,
+    strategy: SamplingStrategy,
+    opts: &TranscribeOptions,
+) -> Result<Transcript> {
+    let mut params = FullParams::new(strategy);
     params.set_language(Some(language));
     params.set_print_special(false);
     params.set_print_progress(false);
     params.set_print_realtime(false);
     params.set_print_timestamps(false);
+    params.set_token_timestamps(opts.tokens);
+    params.set_entropy_thold(opts.entropy_thold);
+    params.set_logprob_thold(opts.logprob_thold);
+    params.set_suppress_blank(opts.suppress_blank);
+    params.set_suppress_nst(opts.suppress_non_speech);
 
     state
         .full(params, audio)
         .context("whisper transcription failed")?;
 
     let n_segments = state.full_n_segments();
+    // Token ids from end-of-text upwards are special/timestamp tokens.
+    let eot = ctx.token_eot();
+
+    let decoded_language = if language == "auto" {
+        whisper_rs::get_lang_str(state.full_lang_id_from_state()).unwrap_or(language)
+    } else {
+        language
+    };
+    let default_blocklist = hallucination::defaults(decoded_language);
+    let mut hallucinations_removed = false;
 
     let mut text = String::new();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut has_dtw = false;
+    let mut prob_sum = 0.0;
+    let mut n_tokens = 0;
     for i in 0..n_segments {
         let segment = state
             .get_segment(i)
@@ -36,8 +282,329 @@ pub fn transcribe_with_context(ctx: &WhisperContext, audio: &[f32], language: &s
         let segment_text = segment
             .to_str()
             .map_err(|e| anyhow::anyhow!("failed to get segment text: {e}"))?;
-        text.push_str(segment_text);
+
+        let stripped = if segment.no_speech_probability() >= opts.no_speech_thold {
+            match &opts.hallucinations {
+                Some(list) => hallucination::strip(segment_text, list),
+                None => hallucination::strip(segment_text, default_blocklist),
+            }
+        } else {
+            None
+        };
+        match stripped {
+            // The whole segment was a hallucination; drop its tokens too.
+            Some(rest) if rest.is_empty() => {
+                hallucinations_removed = true;
+                continue;
+            }
+            Some(rest) => {
+                hallucinations_removed = true;
+                text.push(' ');
+                text.push_str(&rest);
+            }
+            None => text.push_str(segment_text),
+        }
+
+        for t in 0..segment.n_tokens() {
+            if let Some(token) = segment.get_token(t)
+                && token.token_id() < eot
+            {
+                prob_sum += token.token_probability();
+                n_tokens += 1;
+
+                if opts.tokens {
+                    let data = token.token_data();
+                    // DTW gives a single aligned time per token (-1 when disabled).
+                    has_dtw |= data.t_dtw >= 0;
+                    let start = if data.t_dtw >= 0 { data.t_dtw } else { data.t0 };
+                    tokens.push(Token {
+                        text: token
+                            .to_str_lossy()
+                            .map_err(|e| anyhow::anyhow!("failed to get token text: {e}"))?
+                            .into_owned(),
+                        // Whisper timestamps are in centiseconds.
+                        start_ms: start * 10,
+                        end_ms: data.t1 * 10,
+                        probability: data.p,
+                    });
+                }
+            }
+        }
+    }
+
+    if has_dtw {
+        end_at_next_start(&mut tokens);
     }
 
-    Ok(text.trim().to_string())
-}
\ No newline at end of file
+    let confidence = if n_tokens == 0 {
+        0.0
+    } else {
+        prob_sum / n_tokens as f32
+    };
+    Ok(Transcript {
+        text: text.trim().to_string(),
+        language: decoded_language.to_string(),
+        confidence,
+        retried: false,
+        tokens,
+        loop_removed: false,
+        hallucinations_removed,
+    })
+}
+
+/// Run Whisper's language detection and return the most probable language in `allowed`.
+fn detect_allowed_language(
+    state: &mut WhisperState,
+    audio: &[f32],
+    allowed: &[String],
+) -> Result<String> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get().min(4));
+    state
+        .pcm_to_mel(audio, threads)
+        .context("failed to compute mel spectrogram")?;
+    let (detected_id, probs) = state
+        .lang_detect(0, threads)
+        .context("language detection failed")?;
+
+    let best = allowed
+        .iter()
+        .filter_map(|lang| {
+            let id = whisper_rs::get_lang_id(lang)?;
+            Some((lang, *probs.get(id as usize)?))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(lang, _)| lang.clone())
+        .context("none of the allowed languages are known to whisper")?;
+
+    let detected = whisper_rs::get_lang_str(detected_id).unwrap_or("unknown");
+    if detected != best {
+        eprintln!("[stt-typer] detected language {detected} is not allowed, using {best}");
+    }
+    Ok(best)
+}
+
+/// DTW gives each token a single aligned time: a token lasts until the next one
+/// starts. Out-of-order times keep Whisper's own end time.
+fn end_at_next_start(tokens: &mut [Token]) {
+    for i in 1..tokens.len() {
+        let next_start = tokens[i].start_ms;
+        if next_start >= tokens[i - 1].start_ms {
+            tokens[i - 1].end_ms = next_start;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::ValueEnum;
+
+    fn token(start_ms: i64, end_ms: i64) -> Token {
+        Token {
+            text: String::new(),
+            start_ms,
+            end_ms,
+            probability: 1.0,
+        }
+    }
+
+    #[test]
+    fn dtw_tokens_end_where_the_next_one_starts() {
+        let mut tokens = vec![
+            token(0, 100),
+            token(300, 400),
+            token(250, 600),
+            token(500, 700),
+        ];
+        end_at_next_start(&mut tokens);
+        let ends: Vec<i64> = tokens.iter().map(|t| t.end_ms).collect();
+        assert_eq!(ends, [300, 400, 500, 700]);
+    }
+
+    #[test]
+    fn dtw_presets_use_model_names() {
+        assert!(matches!(
+            DtwPreset::from_str("base.en", false),
+            Ok(DtwPreset::BaseEn)
+        ));
+        assert!(matches!(
+            DtwPreset::from_str("large-v3-turbo", false),
+            Ok(DtwPreset::LargeV3Turbo)
+        ));
+        assert!(DtwPreset::from_str("base-en", false).is_err());
+    }
+
+    #[test]
+    fn collapses_single_word_loop() {
+        let looped = format!("Okay{}", " okay".repeat(9));
+        assert_eq!(collapse_repetition(&looped).as_deref(), Some("Okay"));
+    }
+
+    #[test]
+    fn collapses_multi_word_loop_and_keeps_surrounding_text() {
+        let text = "Start here. I will go. I will go. I will go. I will go. Done";
+        assert_eq!(
+            collapse_repetition(text).as_deref(),
+            Some("Start here. I will go. Done")
+        );
+    }
+
+    #[test]
+    fn keeps_short_emphatic_repeats() {
+        // Three repeats, but far fewer than LOOP_MIN_WORDS words.
+        assert_eq!(collapse_repetition("No no no, not that one."), None);
+        assert_eq!(collapse_repetition("very very very very good"), None);
+    }
+
+    #[test]
+    fn needs_enough_repeats_of_long_phrases() {
+        // Twelve words, but the phrase only repeats twice.
+        let text = "one two three four five six one two three four five six";
+        assert_eq!(collapse_repetition(text), None);
+        let thrice = format!("{text} one two three four five six");
+        assert_eq!(
+            collapse_repetition(&thrice).as_deref(),
+            Some("one two three four five six")
+        );
+    }
+}
+
+/// End-to-end checks against a real Whisper model. Download one and run:
+/// `WHISPER_TEST_MODEL=~/.local/share/stt-mcp/ggml-tiny.en.bin cargo test --features model-tests`
+#[cfg(all(test, feature = "model-tests"))]
+mod model_tests {
+    use super::*;
+    use crate::test_support::{WavEncoding, interleave, wav};
+    use crate::{audio, pcm};
+
+    /// What whisper.cpp's `samples/jfk.wav` says, in [`words::key`] form.
+    const JFK: &str =
+        "ask not what your country can do for you ask what you can do for your country";
+
+    fn context() -> WhisperContext {
+        let path = std::env::var("WHISPER_TEST_MODEL")
+            .expect("set WHISPER_TEST_MODEL to a ggml model, e.g. ggml-tiny.en.bin");
+        create_context(Path::new(&path), None).unwrap()
+    }
+
+    /// The speech fixture as 16kHz mono.
+    fn speech() -> Vec<f32> {
+        let path = std::env::var("WHISPER_TEST_SPEECH")
+            .expect("set WHISPER_TEST_SPEECH to whisper.cpp's samples/jfk.wav");
+        let decoded = pcm::decode_wav(&std::fs::read(path).unwrap()).unwrap();
+        audio::to_whisper_format(
+            decoded.samples,
+            decoded.rate,
+            decoded.channels as usize,
+            audio::ResampleQuality::High,
+        )
+    }
+
+    fn transcribe(samples: &[f32], language: &str) -> Transcript {
+        let ctx = context();
+        let mut state = ctx.create_state().unwrap();
+        let opts = TranscribeOptions {
+            language: language.to_string(),
+            ..options()
+        };
+        transcribe_with_state(&ctx, &mut state, samples, &opts).unwrap()
+    }
+
+    fn options() -> TranscribeOptions {
+        TranscribeOptions {
+            language: "en".to_string(),
+            allowed_languages: Vec::new(),
+            retry_below: None,
+            tokens: true,
+            hallucinations: None,
+            no_speech_thold: 0.6,
+            entropy_thold: 2.4,
+            logprob_thold: -1.0,
+            suppress_blank: true,
+            suppress_non_speech: false,
+        }
+    }
+
+    fn assert_says_jfk(transcript: &Transcript) {
+        let keys: Vec<String> = transcript.text.split_whitespace().map(words::key).collect();
+        assert!(keys.join(" ").contains(JFK), "{:?}", transcript.text);
+    }
+
+    #[test]
+    fn speech_is_transcribed() {
+        let transcript = transcribe(&speech(), "en");
+        assert_says_jfk(&transcript);
+        assert!(
+            transcript.confidence > 0.5,
+            "confidence {}",
+            transcript.confidence
+        );
+        assert!(!transcript.loop_removed && !transcript.hallucinations_removed);
+    }
+
+    #[test]
+    fn language_is_detected() {
+        let transcript = transcribe(&speech(), "auto");
+        assert_eq!(transcript.language, "en");
+        assert_says_jfk(&transcript);
+    }
+
+    #[test]
+    fn wav_file_through_resampler_and_whisper() {
+        // 48kHz stereo by repeating each 16kHz sample, so the resampler has to
+        // filter out the images as well as convert the rate.
+        let speech = speech();
+        let upsampled: Vec<f32> = speech.iter().flat_map(|&s| [s; 3]).collect();
+        let bytes = wav(
+            &interleave(&[upsampled.clone(), upsampled]),
+            48000,
+            2,
+            WavEncoding::Pcm16,
+        );
+        let raw = pcm::RawPcm {
+            rate: 16000,
+            channels: 1,
+            format: pcm::PcmFormat::S16le,
+        };
+        let decoded = pcm::decode(&bytes, &raw).unwrap();
+        let samples = audio::to_whisper_format(
+            decoded.samples,
+            decoded.rate,
+            decoded.channels as usize,
+            audio::ResampleQuality::High,
+        );
+        assert!(samples.len().abs_diff(speech.len()) <= 1);
+        assert_says_jfk(&transcribe(&samples, "en"));
+    }
+
+    #[test]
+    fn token_timestamps_are_ordered_and_within_audio() {
+        let mut samples = vec![0.0; 8000];
+        samples.extend(speech());
+        let transcript = transcribe(&samples, "en");
+        assert!(!transcript.tokens.is_empty());
+        let duration_ms = samples.len() as i64 / 16;
+        for token in &transcript.tokens {
+            assert!(
+                token.start_ms <= token.end_ms,
+                "{}: {}..{}",
+                token.text,
+                token.start_ms,
+                token.end_ms
+            );
+            assert!(token.end_ms <= duration_ms + 1000);
+        }
+    }
+
+    #[test]
+    fn reused_state_gives_the_same_transcript() {
+        let ctx = context();
+        let mut state = ctx.create_state().unwrap();
+        let samples = speech();
+        let first = transcribe_with_state(&ctx, &mut state, &samples, &options()).unwrap();
+        let second = transcribe_with_state(&ctx, &mut state, &samples, &options()).unwrap();
+        assert_says_jfk(&first);
+        assert_eq!(first.text, second.text);
+        assert_eq!(first.tokens.len(), second.tokens.len());
+    }
+}
@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use whisper_rs::{WhisperVadContext, WhisperVadContextParams, WhisperVadParams};
+
+/// Silero voice activity detector (whisper.cpp's built-in VAD), loaded once and
+/// reused for every recording.
+pub struct SpeechDetector {
+    ctx: WhisperVadContext,
+    params: WhisperVadParams,
+}
+
+impl SpeechDetector {
+    /// Load a ggml Silero VAD model. `threshold` is the speech probability (0–1)
+    /// above which a frame counts as speech.
+    pub fn new(model_path: &Path, threshold: f32) -> Result<Self> {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get().min(4));
+        let mut ctx_params = WhisperVadContextParams::new();
+        ctx_params.set_n_threads(threads as i32);
+        let ctx = WhisperVadContext::new(model_path.to_str().unwrap_or_default(), ctx_params)
+            .context("failed to load VAD model")?;
+
+        let mut params = WhisperVadParams::new();
+        params.set_threshold(threshold);
+
+        Ok(SpeechDetector { ctx, params })
+    }
+
+    /// Return only the speech in `samples` (16kHz mono), with silence and
+    /// background noise between segments cut out. Empty if no speech was found.
+    pub fn speech_only(&mut self, samples: &[f32]) -> Result<Vec<f32>> {
+        let segments = self
+            .ctx
+            .segments_from_samples(self.params, samples)
+            .context("voice activity detection failed")?;
+        Ok(cut_segments(samples, segments.map(|s| (s.start, s.end))))
+    }
+}
+
+/// Concatenate the `(start, end)` spans of `samples`, given in centiseconds.
+fn cut_segments(samples: &[f32], segments: impl IntoIterator<Item = (f32, f32)>) -> Vec<f32> {
+    let mut speech = Vec::new();
+    for (start, end) in segments {
+        // 160 samples per centisecond at 16kHz.
+        let start = ((start * 160.0) as usize).min(samples.len());
+        let end = ((end * 160.0) as usize).min(samples.len());
+        speech.extend_from_slice(&samples[start..end.max(start)]);
+    }
+    speech
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_only_the_speech_segments() {
+        let samples: Vec<f32> = (0..1600).map(|i| i as f32).collect();
+        // 1–2 cs and 5–6 cs: samples 160..320 and 800..960.
+        let speech = cut_segments(&samples, [(1.0, 2.0), (5.0, 6.0)]);
+        assert_eq!(speech.len(), 320);
+        assert_eq!(speech[0], 160.0);
+        assert_eq!(speech[159], 319.0);
+        assert_eq!(speech[160], 800.0);
+    }
+
+    #[test]
+    fn clamps_segments_to_the_recording() {
+        let samples = vec![0.5; 1600];
+        assert_eq!(cut_segments(&samples, [(8.0, 20.0)]).len(), 320);
+        assert!(cut_segments(&samples, [(12.0, 20.0), (6.0, 4.0)]).is_empty());
+        assert!(cut_segments(&samples, std::iter::empty()).is_empty());
+    }
+}
@@ -0,0 +1,80 @@
+//! Watch-folder transcription (`stt-typer watch`): every WAV file dropped into a
+//! directory is transcribed into a `.txt` file next to it.
+
+use crate::{audio, pcm, pipeline::Pipeline};
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Poll `dir` every `interval` and transcribe WAV files that have no transcript yet.
+/// A file is picked up once its size stops changing, so copies in progress are not
+/// read half-written. Runs until killed.
+pub fn watch(
+    pipeline: &mut Pipeline,
+    dir: &Path,
+    interval: Duration,
+    quality: audio::ResampleQuality,
+) -> Result<()> {
+    // Size seen on the previous scan, per pending file.
+    let mut sizes: HashMap<PathBuf, u64> = HashMap::new();
+    // Files that failed; not retried until the process restarts.
+    let mut failed: HashSet<PathBuf> = HashSet::new();
+
+    eprintln!("[stt-typer] watching {} for new WAV files", dir.display());
+    loop {
+        let entries = std::fs::read_dir(dir)
+            .with_context(|| format!("failed to read directory {}", dir.display()))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_wav = path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("wav"));
+            if !is_wav || failed.contains(&path) || path.with_extension("txt").exists() {
+                continue;
+            }
+            let Ok(size) = entry.metadata().map(|m| m.len()) else {
+                continue;
+            };
+            if sizes.insert(path.clone(), size) != Some(size) {
+                continue; // new or still growing
+            }
+
+            sizes.remove(&path);
+            if let Err(e) = transcribe_file(pipeline, &path, quality) {
+                eprintln!("[stt-typer] {}: {e:#}", path.display());
+                failed.insert(path);
+            }
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+/// Transcribe `path` and write the text to the matching `.txt` file. An empty
+/// transcript still writes the file so it isn't picked up again.
+fn transcribe_file(
+    pipeline: &mut Pipeline,
+    path: &Path,
+    quality: audio::ResampleQuality,
+) -> Result<()> {
+    eprintln!("[stt-typer] transcribing {}", path.display());
+    let bytes = std::fs::read(path).context("failed to read file")?;
+    let decoded = pcm::decode_wav(&bytes)?;
+    let samples = audio::to_whisper_format(
+        decoded.samples,
+        decoded.rate,
+        decoded.channels as usize,
+        quality,
+    );
+    let text = pipeline.process(samples)?.unwrap_or_default();
+
+    let out = path.with_extension("txt");
+    let contents = if text.is_empty() {
+        text
+    } else {
+        format!("{text}\n")
+    };
+    std::fs::write(&out, contents).with_context(|| format!("failed to write {}", out.display()))?;
+    eprintln!("[stt-typer] wrote {}", out.display());
+    Ok(())
+}
@@ -0,0 +1,11 @@
+//! Word matching shared by the text passes (hallucination blocklist, loop
+//! removal, replacement dictionary).
+
+/// The word without punctuation, lowercased, for case- and punctuation-insensitive
+/// comparison.
+pub fn key(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}